@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS quote_versions (
+    quote_id UUID NOT NULL REFERENCES quotes (id) ON DELETE CASCADE,
+    version INT NOT NULL,
+    author TEXT NOT NULL,
+    quote TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (quote_id, version)
+);
+
+-- Earlier versions are lost, keep at least the current one.
+INSERT INTO quote_versions (quote_id, version, author, quote, created_at)
+SELECT id, version, author, quote, created_at FROM quotes
+ON CONFLICT DO NOTHING;
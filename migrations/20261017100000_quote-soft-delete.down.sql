@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS quotes_deleted_at_idx;
+ALTER TABLE quotes DROP COLUMN IF EXISTS deleted_at;
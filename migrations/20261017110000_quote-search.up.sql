@@ -0,0 +1,8 @@
+-- Add up migration script here
+ALTER TABLE quotes ADD COLUMN IF NOT EXISTS search TSVECTOR
+GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', author), 'A') ||
+    setweight(to_tsvector('english', quote), 'B')
+) STORED;
+
+CREATE INDEX IF NOT EXISTS quotes_search_idx ON quotes USING GIN (search);
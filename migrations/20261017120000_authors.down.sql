@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE quotes DROP COLUMN IF EXISTS author_id;
+DROP TABLE IF EXISTS authors;
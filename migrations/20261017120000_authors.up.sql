@@ -0,0 +1,16 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS authors (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- Spelling of the author's first quote.
+    name TEXT NOT NULL,
+    -- Lowercased with whitespace trimmed and collapsed, see `normalize_author`.
+    normalized_name TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Nullable because existing quotes are filed by `file_authors` once the
+-- migrations have run, using `normalize_author` itself rather than an
+-- approximation in SQL. Every write sets it.
+ALTER TABLE quotes ADD COLUMN IF NOT EXISTS author_id UUID REFERENCES authors (id);
+
+CREATE INDEX IF NOT EXISTS quotes_author_id_idx ON quotes (author_id);
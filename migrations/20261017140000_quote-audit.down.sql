@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS quote_audit;
+DROP FUNCTION IF EXISTS quote_audit_append_only();
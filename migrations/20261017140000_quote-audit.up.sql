@@ -0,0 +1,28 @@
+-- Add up migration script here
+-- No foreign key: entries outlive purged quotes and resets.
+CREATE TABLE IF NOT EXISTS quote_audit (
+    id BIGSERIAL PRIMARY KEY,
+    action TEXT NOT NULL,
+    quote_id UUID,
+    before JSONB,
+    after JSONB,
+    actor TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS quote_audit_quote_id_idx ON quote_audit (quote_id);
+CREATE INDEX IF NOT EXISTS quote_audit_created_at_idx ON quote_audit (created_at);
+
+CREATE OR REPLACE FUNCTION quote_audit_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'quote_audit is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER quote_audit_append_only
+BEFORE UPDATE OR DELETE ON quote_audit
+FOR EACH ROW EXECUTE FUNCTION quote_audit_append_only();
+
+CREATE OR REPLACE TRIGGER quote_audit_no_truncate
+BEFORE TRUNCATE ON quote_audit
+FOR EACH STATEMENT EXECUTE FUNCTION quote_audit_append_only();
@@ -0,0 +1,9 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS quote_votes (
+    quote_id UUID NOT NULL REFERENCES quotes (id) ON DELETE CASCADE,
+    voter TEXT NOT NULL,
+    -- 1 for an upvote, -1 for a downvote.
+    value SMALLINT NOT NULL CHECK (value IN (-1, 1)),
+    voted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (quote_id, voter)
+);
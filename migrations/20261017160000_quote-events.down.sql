@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS quote_events_record ON quotes;
+DROP FUNCTION IF EXISTS quote_events_record();
+DROP TABLE IF EXISTS quote_events;
@@ -0,0 +1,50 @@
+-- Add up migration script here
+-- Snapshots of quotes as they changed, kept for `Last-Event-ID` resumes.
+CREATE TABLE IF NOT EXISTS quote_events (
+    id BIGSERIAL PRIMARY KEY,
+    kind TEXT NOT NULL CHECK (kind IN ('created', 'updated', 'deleted')),
+    quote_id UUID NOT NULL,
+    author TEXT NOT NULL,
+    quote TEXT NOT NULL,
+    quote_created_at TIMESTAMPTZ NOT NULL,
+    version INT NOT NULL,
+    deleted_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS quote_events_created_at_idx ON quote_events (created_at);
+
+-- Only the event id goes into the notification, payloads are capped at 8000
+-- bytes.
+CREATE OR REPLACE FUNCTION quote_events_record() RETURNS trigger AS $$
+DECLARE
+    event_kind TEXT;
+    event_id BIGINT;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        IF NEW.deleted_at IS NULL THEN
+            event_kind := 'created';
+        END IF;
+    ELSIF OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL THEN
+        event_kind := 'deleted';
+    ELSIF OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN
+        -- A restored quote reappears as if it were new.
+        event_kind := 'created';
+    ELSIF NEW.deleted_at IS NULL THEN
+        event_kind := 'updated';
+    END IF;
+
+    IF event_kind IS NOT NULL THEN
+        INSERT INTO quote_events (kind, quote_id, author, quote, quote_created_at, version, deleted_at)
+        VALUES (event_kind, NEW.id, NEW.author, NEW.quote, NEW.created_at, NEW.version, NEW.deleted_at)
+        RETURNING id INTO event_id;
+        PERFORM pg_notify('quote_events', event_id::TEXT);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Bookkeeping columns such as `author_id` change without an event.
+CREATE OR REPLACE TRIGGER quote_events_record
+AFTER INSERT OR UPDATE OF author, quote, version, deleted_at ON quotes
+FOR EACH ROW EXECUTE FUNCTION quote_events_record();
@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS quote_versions (
+    quote_id BLOB NOT NULL REFERENCES quotes (id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    author TEXT NOT NULL,
+    quote TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (quote_id, version)
+);
+
+-- Earlier versions are lost, keep at least the current one.
+INSERT OR IGNORE INTO quote_versions (quote_id, version, author, quote, created_at)
+SELECT id, version, author, quote, created_at FROM quotes;
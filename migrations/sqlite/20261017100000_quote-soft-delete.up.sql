@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE quotes ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX IF NOT EXISTS quotes_deleted_at_idx ON quotes (deleted_at)
+WHERE deleted_at IS NOT NULL;
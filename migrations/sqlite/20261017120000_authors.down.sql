@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS quotes_author_id_idx;
+ALTER TABLE quotes DROP COLUMN author_id;
+DROP TABLE IF EXISTS authors;
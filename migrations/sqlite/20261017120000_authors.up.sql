@@ -0,0 +1,16 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS authors (
+    id BLOB PRIMARY KEY,
+    -- Spelling of the author's first quote.
+    name TEXT NOT NULL,
+    -- Lowercased with whitespace trimmed and collapsed, see `normalize_author`.
+    normalized_name TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL
+);
+
+-- Nullable because existing quotes are filed by `file_authors` once the
+-- migrations have run, using `normalize_author` itself; SQLite can't collapse
+-- whitespace. Every write sets it.
+ALTER TABLE quotes ADD COLUMN author_id BLOB REFERENCES authors (id);
+
+CREATE INDEX IF NOT EXISTS quotes_author_id_idx ON quotes (author_id);
@@ -0,0 +1,26 @@
+-- Add up migration script here
+-- No foreign key: entries outlive purged quotes and resets.
+CREATE TABLE IF NOT EXISTS quote_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    action TEXT NOT NULL,
+    quote_id BLOB,
+    before TEXT,
+    after TEXT,
+    actor TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS quote_audit_quote_id_idx ON quote_audit (quote_id);
+CREATE INDEX IF NOT EXISTS quote_audit_created_at_idx ON quote_audit (created_at);
+
+CREATE TRIGGER IF NOT EXISTS quote_audit_no_update
+BEFORE UPDATE ON quote_audit
+BEGIN
+    SELECT RAISE(ABORT, 'quote_audit is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS quote_audit_no_delete
+BEFORE DELETE ON quote_audit
+BEGIN
+    SELECT RAISE(ABORT, 'quote_audit is append-only');
+END;
@@ -0,0 +1,9 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS quote_votes (
+    quote_id BLOB NOT NULL REFERENCES quotes (id) ON DELETE CASCADE,
+    voter TEXT NOT NULL,
+    -- 1 for an upvote, -1 for a downvote.
+    value INTEGER NOT NULL CHECK (value IN (-1, 1)),
+    voted_at TEXT NOT NULL,
+    PRIMARY KEY (quote_id, voter)
+);
@@ -4,9 +4,11 @@ use std::{
     str::FromStr,
 };
 
-use axum::extract::Query;
+use axum::extract::{rejection::QueryRejection, Query};
 use serde::{de, Deserialize, Deserializer};
 
+use crate::error::AppError;
+
 #[derive(Deserialize)]
 pub struct Ipv4Params {
     from: Option<Ipv4Addr>,
@@ -40,38 +42,51 @@ where
     }
 }
 
-pub async fn ipv4_encryption(ipv4: Query<Ipv4Params>) -> String {
-    let from = ipv4.from.expect("Error: from is not set.").octets();
-    let key = ipv4.key.expect("Error: key is not set.").octets();
+fn required<T>(value: Option<T>, name: &str) -> Result<T, AppError> {
+    value.ok_or_else(|| AppError::BadRequest(format!("{name} is not set.")))
+}
+
+pub async fn ipv4_encryption(
+    ipv4: Result<Query<Ipv4Params>, QueryRejection>,
+) -> Result<String, AppError> {
+    let ipv4 = ipv4?;
+    let from = required(ipv4.from, "from")?.octets();
+    let key = required(ipv4.key, "key")?.octets();
     let to: Vec<_> = from
         .iter()
         .zip(key.iter())
         .map(|(x, y)| x.wrapping_add(*y).to_string())
         .collect();
-    to.join(".")
+    Ok(to.join("."))
 }
 
-pub async fn extract_ipv4_key(ipv4: Query<Ipv4Params>) -> String {
-    let from = ipv4.from.expect("Error: from is not set.").octets();
-    let to = ipv4.to.expect("Error: to is not set.").octets();
+pub async fn extract_ipv4_key(
+    ipv4: Result<Query<Ipv4Params>, QueryRejection>,
+) -> Result<String, AppError> {
+    let ipv4 = ipv4?;
+    let from = required(ipv4.from, "from")?.octets();
+    let to = required(ipv4.to, "to")?.octets();
     let key: Vec<_> = from
         .iter()
         .zip(to.iter())
         .map(|(x, y)| y.wrapping_sub(*x).to_string())
         .collect();
-    key.join(".")
+    Ok(key.join("."))
 }
 
-pub async fn ipv6_encryption(ipv6: Query<Ipv6Params>) -> String {
-    let from = ipv6.from.expect("Error: from is not set.").segments();
-    let key = ipv6.key.expect("Error: key is not set.").segments();
+pub async fn ipv6_encryption(
+    ipv6: Result<Query<Ipv6Params>, QueryRejection>,
+) -> Result<String, AppError> {
+    let ipv6 = ipv6?;
+    let from = required(ipv6.from, "from")?.segments();
+    let key = required(ipv6.key, "key")?.segments();
     let result_segments: Vec<u16> = from
         .iter()
         .zip(key.iter())
         .map(|(&seg1, &seg2)| seg1 ^ seg2)
         .collect();
 
-    Ipv6Addr::new(
+    Ok(Ipv6Addr::new(
         result_segments[0],
         result_segments[1],
         result_segments[2],
@@ -81,19 +96,22 @@ pub async fn ipv6_encryption(ipv6: Query<Ipv6Params>) -> String {
         result_segments[6],
         result_segments[7],
     )
-    .to_string()
+    .to_string())
 }
 
-pub async fn extract_ipv6_key(ipv6: Query<Ipv6Params>) -> String {
-    let from = ipv6.from.expect("Error: from is not set.").segments();
-    let to = ipv6.to.expect("Error: to is not set.").segments();
+pub async fn extract_ipv6_key(
+    ipv6: Result<Query<Ipv6Params>, QueryRejection>,
+) -> Result<String, AppError> {
+    let ipv6 = ipv6?;
+    let from = required(ipv6.from, "from")?.segments();
+    let to = required(ipv6.to, "to")?.segments();
     let result_segments: Vec<u16> = from
         .iter()
         .zip(to.iter())
         .map(|(&seg1, &seg2)| seg1 ^ seg2)
         .collect();
 
-    Ipv6Addr::new(
+    Ok(Ipv6Addr::new(
         result_segments[0],
         result_segments[1],
         result_segments[2],
@@ -103,5 +121,5 @@ pub async fn extract_ipv6_key(ipv6: Query<Ipv6Params>) -> String {
         result_segments[6],
         result_segments[7],
     )
-    .to_string()
+    .to_string())
 }
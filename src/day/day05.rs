@@ -1,47 +1,48 @@
 use axum::{
     http::{header, HeaderMap, StatusCode},
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
 use cargo_manifest::Manifest;
 use toml::Value;
 
-pub async fn parse_manifest(
-    headers: HeaderMap,
-    body: String,
-) -> Result<impl IntoResponse, impl IntoResponse> {
-    // dbg!(headers);
+use crate::error::AppError;
+
+// Bodies the day 5 validator checks word for word, so unlike the other
+// errors here they aren't problem documents.
+fn invalid_manifest() -> Result<Response, AppError> {
+    Ok((StatusCode::BAD_REQUEST, "Invalid manifest").into_response())
+}
+
+fn no_magic_keyword() -> Result<Response, AppError> {
+    Ok((StatusCode::BAD_REQUEST, "Magic keyword not provided").into_response())
+}
+
+pub async fn parse_manifest(headers: HeaderMap, body: String) -> Result<Response, AppError> {
     let content_type = headers
         .get(header::CONTENT_TYPE)
-        .expect("Error: content-type isn't exist in header.")
+        .ok_or_else(|| AppError::UnsupportedMediaType("Content-Type is not specified".into()))?
         .to_str()
-        .expect("Error: content-type isn't parsable to string.");
+        .map_err(|_| AppError::BadRequest("Content-Type is not parsable to string".into()))?;
 
     let mut ret = vec![];
-    let manifest: Manifest = match content_type {
-        "application/toml" => toml::from_str(&body)
-            .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid manifest").into_response())?,
-        "application/yaml" => serde_yaml::from_str(&body)
-            .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid manifest").into_response())?,
-        "application/json" => serde_json::from_str(&body)
-            .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid manifest").into_response())?,
-        _ => {
-            return Err((StatusCode::UNSUPPORTED_MEDIA_TYPE).into_response());
-        }
+    let manifest: Option<Manifest> = match content_type {
+        "application/toml" => toml::from_str(&body).ok(),
+        "application/yaml" => serde_yaml::from_str(&body).ok(),
+        "application/json" => serde_json::from_str(&body).ok(),
+        _ => return Ok(StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response()),
     };
 
-    let Some(package) = manifest.package else {
-        return Err((StatusCode::BAD_REQUEST, "Invalid manifest").into_response());
+    let Some(package) = manifest.and_then(|manifest| manifest.package) else {
+        return invalid_manifest();
     };
     let Some(keywords) = package.keywords else {
-        return Err((StatusCode::BAD_REQUEST, "Magic keyword not provided").into_response());
+        return no_magic_keyword();
+    };
+    let Some(keywords) = keywords.as_local() else {
+        return no_magic_keyword();
     };
-    if !keywords
-        .as_local()
-        .unwrap()
-        .iter()
-        .any(|x| x == "Christmas 2024")
-    {
-        return Err((StatusCode::BAD_REQUEST, "Magic keyword not provided").into_response());
+    if !keywords.iter().any(|x| x == "Christmas 2024") {
+        return no_magic_keyword();
     }
     let Some(metadata) = package.metadata else {
         return Ok(StatusCode::NO_CONTENT.into_response());
@@ -71,7 +72,7 @@ pub async fn parse_manifest(
                     // dbg!(&order);
                     ret.push(format!("{}: {}", item, quantity));
                 }
-                _ => panic!("Error: Input format is not correct."),
+                _ => return Err(AppError::BadRequest("Invalid orders".into())),
             }
         }
     } else {
@@ -79,7 +80,7 @@ pub async fn parse_manifest(
     }
     // dbg!(&ret);
 
-    if ret.len() == 0 {
+    if ret.is_empty() {
         Ok(StatusCode::NO_CONTENT.into_response())
     } else {
         Ok((StatusCode::OK, ret.join("\n")).into_response())
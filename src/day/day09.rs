@@ -31,7 +31,9 @@ pub async fn withdraw_milk(
     headers: HeaderMap,
     State(state): State<Arc<AppState>>,
     body: String,
-) -> Result<String, impl IntoResponse> {
+) -> Result<String, Response> {
+    // The challenge validator compares these error bodies verbatim, so they
+    // stay plain text rather than problem documents.
     if !state.milk_amount.read().try_acquire(1) {
         return Err((StatusCode::TOO_MANY_REQUESTS, "No milk available\n").into_response());
     }
@@ -52,21 +54,21 @@ pub async fn withdraw_milk(
 
         match (json.liters, json.gallons, json.litres, json.pints) {
             (None, Some(gallons), None, None) => {
-                return Ok(format!("{{\"liters\":{}}}\n", gallons * 3.785_412_5))
+                Ok(format!("{{\"liters\":{}}}\n", gallons * 3.785_412_5))
             }
             (Some(litters), None, None, None) => {
-                return Ok(format!("{{\"gallons\":{}}}\n", litters / 3.785_412_5));
+                Ok(format!("{{\"gallons\":{}}}\n", litters / 3.785_412_5))
             }
             (None, None, Some(litres), None) => {
-                return Ok(format!("{{\"pints\":{}}}\n", litres * 1.759_754))
+                Ok(format!("{{\"pints\":{}}}\n", litres * 1.759_754))
             }
             (None, None, None, Some(pints)) => {
-                return Ok(format!("{{\"litres\":{}}}\n", pints / 1.759_754))
+                Ok(format!("{{\"litres\":{}}}\n", pints / 1.759_754))
             }
             _ => unreachable!(),
         }
     } else {
-        Ok(format!("Milk withdrawn\n"))
+        Ok("Milk withdrawn\n".to_string())
     }
 }
 
@@ -1,131 +1,301 @@
 use std::{
+    collections::HashMap,
     fmt::{self, Display},
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    async_trait,
+    body::Bytes,
+    extract::{
+        rejection::{PathRejection, QueryRejection, StringRejection},
+        FromRequestParts, Path, Query, State,
+    },
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
 };
-use parking_lot::lock_api::RwLockUpgradableReadGuard;
+use chrono::{DateTime, Utc};
+use parking_lot::{lock_api::RwLockUpgradableReadGuard, Mutex, RwLock};
 use rand::{rngs::StdRng, Rng, SeedableRng};
-// use tracing::info;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
 
-use crate::AppState;
+use crate::{
+    error::{AppError, InvalidField},
+    AppState,
+};
+
+/// How long a game may go untouched before it is dropped.
+pub const DEFAULT_GAME_TTL: Duration = Duration::from_secs(60 * 60);
+/// How many created games are kept at once before the least recently used
+/// one makes room.
+pub const DEFAULT_GAME_CAPACITY: usize = 10_000;
+const RANDOM_SEED: u64 = 2024;
+/// Worth of a won position to the AI, above any [`Board::heuristic`] score.
+const WIN_SCORE: i64 = 1_000_000;
 
 static WALL: char = '⬜';
 static EMPTY: char = '⬛';
 static COOKIE: char = '🍪';
 static MILK: char = '🥛';
 
-#[derive(Debug, Default)]
+/// Size of a board, how many tiles in a line win and who may move when.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Validate)]
+#[serde(default)]
+pub struct GameRules {
+    #[validate(range(min = 1, max = 16, message = "must be 1 to 16"))]
+    pub width: usize,
+    #[validate(range(min = 1, max = 16, message = "must be 1 to 16"))]
+    pub height: usize,
+    #[validate(range(min = 2, max = 16, message = "must be 2 to 16"))]
+    pub connect: usize,
+    /// Whether teams have to take turns; otherwise anyone may move anytime.
+    pub strict: bool,
+    /// Who moves first in strict mode.
+    pub first: Team,
+    /// Team played by the server, answering every move by the other team.
+    pub ai: Option<Team>,
+    /// How many moves ahead the AI looks at most.
+    #[validate(range(min = 1, max = 10, message = "must be 1 to 10"))]
+    pub ai_depth: usize,
+    /// Milliseconds the AI may think before settling for a shallower search.
+    #[validate(range(min = 1, max = 5000, message = "must be 1 to 5000"))]
+    pub ai_time_ms: u64,
+}
+
+impl Default for GameRules {
+    /// The original 4x4 board, won by four in a row, without turns.
+    fn default() -> Self {
+        GameRules {
+            width: 4,
+            height: 4,
+            connect: 4,
+            strict: false,
+            first: Team::Cookie,
+            ai: None,
+            ai_depth: 6,
+            ai_time_ms: 500,
+        }
+    }
+}
+
+impl GameRules {
+    /// Validates the rules, including that a line of `connect` tiles fits.
+    pub fn check(&self) -> Result<(), AppError> {
+        self.validate()?;
+        if self.connect > self.width.max(self.height) {
+            return Err(AppError::UnprocessableEntity(vec![InvalidField {
+                field: "connect".into(),
+                message: "must fit on the board".into(),
+            }]));
+        }
+        Ok(())
+    }
+}
+
+/// Directions a winning line can run in, as (row, column) steps: rows,
+/// columns, then both diagonals.
+const LINES: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+#[derive(Clone, Debug)]
 pub struct Board {
-    content: [[Tile; 4]; 4],
+    rules: GameRules,
+    /// Row by row, top row first.
+    content: Vec<Tile>,
 }
 
 impl Board {
-    pub fn new() -> Self {
+    pub fn new(rules: GameRules) -> Self {
         Board {
-            content: [[Tile::Empty; 4]; 4],
+            rules,
+            content: vec![Tile::Empty; rules.width * rules.height],
         }
     }
 
-    // fn set_tile(&mut self, column: usize, tile: Tile) {
-    //     assert_ne!(tile, Tile::Empty);
-    //     assert_eq!(self.columns_filled(column), false);
+    fn tile(&self, row: usize, column: usize) -> Tile {
+        self.content[row * self.rules.width + column]
+    }
+
+    fn set_tile(&mut self, row: usize, column: usize, tile: Tile) {
+        self.content[row * self.rules.width + column] = tile;
+    }
 
-    //     let mut columns = self.content[0..4][column];
-    //     for x in &columns {
-    //         info!("{}", x);
-    //     }
-    //     if let Some(x) = columns.iter_mut().find(|x| **x == Tile::Empty) {
-    //         *x = tile;
-    //     }
-    // }
+    /// Drops a tile into a column and returns the row it lands in, or `None`
+    /// when the column is full.
+    fn drop_tile(&mut self, column: usize, tile: Tile) -> Option<usize> {
+        let row = (0..self.rules.height)
+            .rev()
+            .find(|&row| self.tile(row, column) == Tile::Empty)?;
+        self.set_tile(row, column, tile);
+        Some(row)
+    }
 
+    /// The team owning a line of `connect` tiles. Lines are checked rows
+    /// first, then columns, then diagonals, top left first.
     fn which_won(&self) -> Option<Team> {
-        let rows = self.content;
-        // dbg!(rows);
-        if let Some(winner) = Self::judge(&rows) {
-            return Some(winner);
-        }
-
-        let columns = [
-            [
-                self.content[0][0],
-                self.content[1][0],
-                self.content[2][0],
-                self.content[3][0],
-            ],
-            [
-                self.content[0][1],
-                self.content[1][1],
-                self.content[2][1],
-                self.content[3][1],
-            ],
-            [
-                self.content[0][2],
-                self.content[1][2],
-                self.content[2][2],
-                self.content[3][2],
-            ],
-            [
-                self.content[0][3],
-                self.content[1][3],
-                self.content[2][3],
-                self.content[3][3],
-            ],
-        ];
-        // dbg!(columns);
-        if let Some(winner) = Self::judge(&columns) {
-            return Some(winner);
-        }
-
-        let diagonals = [
-            [
-                self.content[0][0],
-                self.content[1][1],
-                self.content[2][2],
-                self.content[3][3],
-            ],
-            [
-                self.content[0][3],
-                self.content[1][2],
-                self.content[2][1],
-                self.content[3][0],
-            ],
-        ];
-        // dbg!(diagonals);
-        if let Some(winner) = Self::judge(&diagonals) {
-            return Some(winner);
+        let GameRules {
+            width,
+            height,
+            connect,
+            ..
+        } = self.rules;
+        for (row_step, column_step) in LINES {
+            for row in 0..height {
+                for column in 0..width {
+                    let Ok(team) = Team::try_from(self.tile(row, column)) else {
+                        continue;
+                    };
+                    let complete = (1..connect as isize).all(|i| {
+                        let r = row as isize + i * row_step;
+                        let c = column as isize + i * column_step;
+                        (0..height as isize).contains(&r)
+                            && (0..width as isize).contains(&c)
+                            && self.tile(r as usize, c as usize) == Tile::from(team)
+                    });
+                    if complete {
+                        return Some(team);
+                    }
+                }
+            }
         }
-
         None
     }
 
-    fn judge(items: &[[Tile; 4]]) -> Option<Team> {
-        for item in items {
-            if item.iter().all(|&t| t == item[0] && item[0] != Tile::Empty) {
-                return Some(item[0].try_into().unwrap());
+    /// Whether the tile at `row`, `column` is part of a line of `connect`.
+    fn wins_through(&self, row: usize, column: usize) -> bool {
+        let tile = self.tile(row, column);
+        let run = |row_step: isize, column_step: isize| {
+            (1..)
+                .take_while(|&i| {
+                    let r = row as isize + i * row_step;
+                    let c = column as isize + i * column_step;
+                    (0..self.rules.height as isize).contains(&r)
+                        && (0..self.rules.width as isize).contains(&c)
+                        && self.tile(r as usize, c as usize) == tile
+                })
+                .count()
+        };
+        LINES.iter().any(|&(row_step, column_step)| {
+            1 + run(row_step, column_step) + run(-row_step, -column_step) >= self.rules.connect
+        })
+    }
+
+    /// How promising the board looks for `team`: every line of `connect`
+    /// cells still open to one team counts the square of its tiles, for
+    /// that team or against it.
+    fn heuristic(&self, team: Team) -> i64 {
+        let GameRules {
+            width,
+            height,
+            connect,
+            ..
+        } = self.rules;
+        let mut score = 0;
+        for (row_step, column_step) in LINES {
+            for row in 0..height {
+                for column in 0..width {
+                    let line: Option<Vec<_>> = (0..connect as isize)
+                        .map(|i| {
+                            let r = row as isize + i * row_step;
+                            let c = column as isize + i * column_step;
+                            ((0..height as isize).contains(&r) && (0..width as isize).contains(&c))
+                                .then(|| self.tile(r as usize, c as usize))
+                        })
+                        .collect();
+                    let Some(line) = line else {
+                        continue;
+                    };
+                    let count = |team: Team| {
+                        line.iter()
+                            .filter(|&&tile| tile == Tile::from(team))
+                            .count() as i64
+                    };
+                    match (count(team), count(team.other())) {
+                        (ours, 0) => score += ours * ours,
+                        (0, theirs) => score -= theirs * theirs,
+                        _ => {}
+                    }
+                }
             }
         }
-        None
+        score
+    }
+
+    /// Minimax in its negamax form with alpha-beta pruning: the best score
+    /// `team` can force looking `depth` moves ahead, and the column that
+    /// gets it. Teams are assumed to take turns. Sooner wins score higher.
+    /// `None` once past the deadline.
+    fn negamax(
+        &mut self,
+        team: Team,
+        depth: usize,
+        mut alpha: i64,
+        beta: i64,
+        deadline: Option<Instant>,
+    ) -> Option<(i64, Option<usize>)> {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return None;
+        }
+        if depth == 0 {
+            return Some((self.heuristic(team), None));
+        }
+        let mut best = (-i64::MAX, None);
+        // Central columns first; they tend to be stronger and prune more.
+        let mut columns: Vec<_> = (0..self.rules.width).collect();
+        columns.sort_by_key(|&column| (2 * column).abs_diff(self.rules.width - 1));
+        for column in columns {
+            let Some(row) = self.drop_tile(column, Tile::from(team)) else {
+                continue;
+            };
+            let score = if self.wins_through(row, column) {
+                Some(WIN_SCORE + depth as i64)
+            } else {
+                self.negamax(team.other(), depth - 1, -beta, -alpha, deadline)
+                    .map(|(score, _)| -score)
+            };
+            self.set_tile(row, column, Tile::Empty);
+            let score = score?;
+            if best.1.is_none() || score > best.0 {
+                best = (score, Some(column));
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        // No room left is a draw.
+        if best.1.is_none() {
+            return Some((0, None));
+        }
+        Some(best)
+    }
+
+    /// The column the AI plays for `team`, or `None` on a full board.
+    /// Searches one move further at a time, up to `depth`, and keeps the
+    /// deepest answer found within `budget`. One move ahead always finishes.
+    fn best_move(&self, team: Team, depth: usize, budget: Duration) -> Option<usize> {
+        let deadline = Instant::now() + budget;
+        let mut board = self.clone();
+        let mut best = None;
+        for depth in 1..=depth {
+            let deadline = Some(deadline).filter(|_| depth > 1);
+            match board.negamax(team, depth, -i64::MAX, i64::MAX, deadline) {
+                Some((_, column)) => best = column,
+                None => break,
+            }
+        }
+        best
     }
 
     fn all_filled(&self) -> bool {
-        !self.content.as_flattened().contains(&Tile::Empty)
+        !self.content.contains(&Tile::Empty)
     }
 
     fn columns_filled(&self, column: usize) -> bool {
-        ![
-            self.content[0][column],
-            self.content[1][column],
-            self.content[2][column],
-            self.content[3][column],
-        ]
-        .iter()
-        .any(|x| *x == Tile::Empty)
+        self.tile(0, column) != Tile::Empty
     }
 
     fn ended(&self) -> bool {
@@ -141,25 +311,19 @@ impl Board {
 
     fn print_result(&self) -> String {
         if let Some(winner) = self.which_won() {
-            return format!(
-                "{}{} wins!\n",
-                self.to_string(),
-                Tile::try_from(winner).unwrap()
-            );
+            return format!("{}{} wins!\n", self, Tile::from(winner));
         }
         if self.all_filled() {
-            return format!("{}No winner.\n", self.to_string(),);
+            format!("{}No winner.\n", self)
         } else {
-            return self.to_string();
+            self.to_string()
         }
     }
 
-    fn gen_random(rand: &mut StdRng) -> Self {
-        let mut res = Self::default();
-        for i in 0..4 {
-            for j in 0..4 {
-                res.content[i][j] = Tile::gen_random(rand);
-            }
+    fn gen_random(rules: GameRules, rand: &mut StdRng) -> Self {
+        let mut res = Self::new(rules);
+        for tile in &mut res.content {
+            *tile = Tile::gen_random(rand);
         }
         res
     }
@@ -168,7 +332,7 @@ impl Board {
 impl Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut ret = String::new();
-        for row in &self.content {
+        for row in self.content.chunks(self.rules.width) {
             ret.push(WALL);
             for tile in row {
                 ret.push_str(&tile.to_string());
@@ -176,25 +340,20 @@ impl Display for Board {
             ret.push(WALL);
             ret.push('\n');
         }
-        ret.push_str(&format!("{WALL}{WALL}{WALL}{WALL}{WALL}{WALL}"));
+        ret.extend(std::iter::repeat_n(WALL, self.rules.width + 2));
         ret.push('\n');
         write!(f, "{ret}")
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 enum Tile {
+    #[default]
     Empty,
     Cookie,
     Milk,
 }
 
-impl Default for Tile {
-    fn default() -> Self {
-        Tile::Empty
-    }
-}
-
 impl Display for Tile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let ret = match self {
@@ -225,11 +384,45 @@ impl Tile {
     }
 }
 
-enum Team {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Team {
     Cookie,
     Milk,
 }
 
+impl Team {
+    fn parse(team: &str) -> Result<Self, AppError> {
+        match team {
+            "cookie" => Ok(Team::Cookie),
+            "milk" => Ok(Team::Milk),
+            other => Err(AppError::BadRequest(format!("Unknown team: {other}"))),
+        }
+    }
+
+    /// Letter standing for the team in move notation.
+    fn letter(self) -> char {
+        match self {
+            Team::Cookie => 'c',
+            Team::Milk => 'm',
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Team::Cookie => "cookie",
+            Team::Milk => "milk",
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            Team::Cookie => Team::Milk,
+            Team::Milk => Team::Cookie,
+        }
+    }
+}
+
 impl TryFrom<Tile> for Team {
     type Error = ();
     fn try_from(value: Tile) -> Result<Self, Self::Error> {
@@ -241,51 +434,658 @@ impl TryFrom<Tile> for Team {
     }
 }
 
-pub async fn current_board(State(state): State<Arc<AppState>>) -> String {
-    state.board.read().print_result()
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Move {
+    team: Team,
+    /// 1 is the leftmost column.
+    column: usize,
+    /// 1 is the bottom row.
+    row: usize,
+    played_at: DateTime<Utc>,
 }
 
-pub async fn reset_board(State(state): State<Arc<AppState>>) -> String {
-    *state.board.write() = Board::new();
-    *state.rand.lock() = StdRng::seed_from_u64(2024);
-    state.board.read().to_string()
+/// Writes moves as team letter and column each, e.g. `c1m2c1`.
+fn notation(moves: &[Move]) -> String {
+    moves
+        .iter()
+        .map(|m| format!("{}{}", m.team.letter(), m.column))
+        .collect()
 }
 
-pub async fn place_item(
-    Path((team, column)): Path<(String, usize)>,
-    State(state): State<Arc<AppState>>,
-) -> Result<String, impl IntoResponse> {
-    let team = match team.as_str() {
-        "cookie" => Ok(Tile::Cookie),
-        "milk" => Ok(Tile::Milk),
-        _ => Err(StatusCode::BAD_REQUEST.into_response()),
-    }?;
+/// Reads [`notation`] back into teams and columns.
+fn parse_notation(moves: &str) -> Result<Vec<(Team, usize)>, String> {
+    let mut parsed = Vec::new();
+    let mut chars = moves.trim().chars().peekable();
+    while let Some(letter) = chars.next() {
+        let team = match letter {
+            'c' => Team::Cookie,
+            'm' => Team::Milk,
+            other => return Err(format!("unknown team letter {other:?}")),
+        };
+        let mut column = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            column.push(digit);
+        }
+        let column = column
+            .parse()
+            .map_err(|_| format!("missing column after {letter:?}"))?;
+        parsed.push((team, column));
+    }
+    Ok(parsed)
+}
+
+/// Why a move can't be played.
+#[derive(Debug)]
+enum IllegalMove {
+    NoSuchColumn,
+    ColumnFull,
+    GameOver,
+    OutOfTurn { turn: Team },
+}
+
+/// A board, the generator behind its random boards and how it came to be.
+#[derive(Debug)]
+pub struct Game {
+    board: Board,
+    rand: StdRng,
+    /// Who moves next, flipped after every move.
+    turn: Team,
+    /// Oldest first. Cleared by random boards, which aren't played.
+    moves: Vec<Move>,
+}
 
-    let column = column
-        .checked_sub(1)
-        .and_then(|x| if x > 3 { None } else { Some(x) })
-        .ok_or_else(|| StatusCode::BAD_REQUEST.into_response())?;
+impl Game {
+    pub fn new(rules: GameRules) -> Self {
+        Game {
+            board: Board::new(rules),
+            rand: StdRng::seed_from_u64(RANDOM_SEED),
+            turn: rules.first,
+            moves: Vec::new(),
+        }
+    }
 
-    let board = state.board.upgradable_read();
+    fn rules(&self) -> GameRules {
+        self.board.rules
+    }
 
-    if board.columns_filled(column) || board.ended() {
-        return Err((StatusCode::SERVICE_UNAVAILABLE, board.print_result()).into_response());
+    /// Checks that `team` may move at all.
+    fn check_turn(&self, team: Team) -> Result<(), IllegalMove> {
+        if self.board.ended() {
+            return Err(IllegalMove::GameOver);
+        }
+        if let Some(turn) = self.next_turn().filter(|&turn| turn != team) {
+            return Err(IllegalMove::OutOfTurn { turn });
+        }
+        Ok(())
     }
-    // board.set_tile(column, team);
-    let mut board = RwLockUpgradableReadGuard::upgrade(board);
-    for row in (0..=3).rev() {
-        if board.content[row][column] == Tile::Empty {
-            board.content[row][column] = team;
-            return Ok(board.print_result());
+
+    /// Checks a move in 1-based `column` and returns the 0-based column.
+    fn check_move(&self, team: Team, column: usize) -> Result<usize, IllegalMove> {
+        let column = column
+            .checked_sub(1)
+            .filter(|&column| column < self.rules().width)
+            .ok_or(IllegalMove::NoSuchColumn)?;
+        self.check_turn(team)?;
+        if self.board.columns_filled(column) {
+            return Err(IllegalMove::ColumnFull);
+        }
+        Ok(column)
+    }
+
+    /// Sets up the search for `team`'s move on a copy of the board.
+    fn search(&self, team: Team, depth: usize, budget: Duration) -> Result<Search, IllegalMove> {
+        self.check_turn(team)?;
+        Ok(Search {
+            board: self.board.clone(),
+            turn: self.turn,
+            team,
+            depth,
+            budget,
+        })
+    }
+
+    /// The AI's answer to a move by `team`, if it plays the other team and
+    /// the game is still on.
+    fn response(&self, team: Team) -> Option<Search> {
+        let ai = self.rules().ai.filter(|&ai| ai != team)?;
+        let GameRules {
+            ai_depth,
+            ai_time_ms,
+            ..
+        } = self.rules();
+        self.search(ai, ai_depth, Duration::from_millis(ai_time_ms))
+            .ok()
+    }
+
+    /// Whether nothing was played since `search` was set up.
+    fn unchanged_since(&self, search: &Search) -> bool {
+        self.turn == search.turn && self.board.content == search.board.content
+    }
+
+    /// Plays a move that passed [`Game::check_move`].
+    fn apply(&mut self, team: Team, column: usize) {
+        let row = self
+            .board
+            .drop_tile(column, Tile::from(team))
+            .expect("checked moves fit");
+        self.turn = team.other();
+        self.moves.push(Move {
+            team,
+            column: column + 1,
+            row: self.rules().height - row,
+            played_at: Utc::now(),
+        });
+    }
+
+    /// Takes back the last move, or against the AI the player's last move
+    /// along with the AI's answers to it, and returns the move taken back.
+    /// With only AI moves left, those go one at a time, so a game the AI
+    /// opened can still be taken back.
+    fn undo(&mut self) -> Option<Move> {
+        let rules = self.rules();
+        let last = self.moves.len().checked_sub(1)?;
+        let from = rules
+            .ai
+            .and_then(|ai| self.moves.iter().rposition(|m| m.team != ai))
+            .unwrap_or(last);
+        let undone: Vec<Move> = self.moves.drain(from..).collect();
+        for m in &undone {
+            self.board
+                .set_tile(rules.height - m.row, m.column - 1, Tile::Empty);
+        }
+        self.turn = self.moves.last().map_or(rules.first, |m| m.team.other());
+        undone.first().copied()
+    }
+
+    /// The board as it was after the first `count` moves.
+    fn board_after(&self, count: usize) -> Board {
+        let mut board = Board::new(self.rules());
+        for m in &self.moves[..count] {
+            board.drop_tile(m.column - 1, Tile::from(m.team));
+        }
+        board
+    }
+
+    /// Whose turn it is, if turns are enforced and the game is still on.
+    fn next_turn(&self) -> Option<Team> {
+        Some(self.turn).filter(|_| self.rules().strict && !self.board.ended())
+    }
+
+    fn body(&self) -> BoardBody {
+        let board = &self.board;
+        let winner = board.which_won();
+        BoardBody {
+            grid: board
+                .content
+                .chunks(self.rules().width)
+                .map(|row| row.iter().map(|&tile| Team::try_from(tile).ok()).collect())
+                .collect(),
+            winner,
+            draw: winner.is_none() && board.all_filled(),
+            next_turn: self.next_turn(),
+            legal_columns: (0..self.rules().width)
+                .filter(|&column| !board.ended() && !board.columns_filled(column))
+                .map(|column| column + 1)
+                .collect(),
+        }
+    }
+
+    /// [`Game::render`] or [`Game::body`], whichever the client asked for.
+    fn show(&self, status: StatusCode, json: bool) -> Response {
+        let vary = [(header::VARY, "accept")];
+        if json {
+            (status, vary, Json(self.body())).into_response()
+        } else {
+            (status, vary, self.render()).into_response()
         }
     }
 
-    // unreachable
-    Ok(String::new())
+    /// The board, its result, and in strict mode who moves next.
+    fn render(&self) -> String {
+        match self.next_turn() {
+            Some(team) => format!(
+                "{}{} to move.\n",
+                self.board.print_result(),
+                Tile::from(team)
+            ),
+            None => self.board.print_result(),
+        }
+    }
+}
+
+/// A pending [`Board::best_move`] call, run off the game's lock so other
+/// requests aren't held up while the AI thinks.
+struct Search {
+    board: Board,
+    turn: Team,
+    team: Team,
+    depth: usize,
+    budget: Duration,
+}
+
+impl Search {
+    /// Runs on the blocking pool and returns the 0-based column.
+    async fn run(&self) -> Result<usize, AppError> {
+        let board = self.board.clone();
+        let (team, depth, budget) = (self.team, self.depth, self.budget);
+        tokio::task::spawn_blocking(move || {
+            board
+                .best_move(team, depth, budget)
+                .expect("unfinished games have room")
+        })
+        .await
+        .map_err(|err| AppError::Internal(format!("AI move failed: {err}")))
+    }
+}
+
+/// Plays the AI's answer, unless the game moved on while it was thinking.
+async fn answer(game: &SharedGame, search: Option<Search>) -> Result<(), AppError> {
+    let Some(search) = search else {
+        return Ok(());
+    };
+    let column = search.run().await?;
+    let mut game = game.write();
+    if game.unchanged_since(&search) {
+        game.apply(search.team, column);
+    }
+    Ok(())
+}
+
+pub type SharedGame = Arc<RwLock<Game>>;
+
+/// Games created through `POST /12/games`, plus the default game behind the
+/// original routes, which never expires.
+#[derive(Debug)]
+pub struct Games {
+    default: SharedGame,
+    /// Each game with when it was last used.
+    games: Mutex<HashMap<Uuid, (SharedGame, Instant)>>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl Default for Games {
+    fn default() -> Self {
+        Self::new(DEFAULT_GAME_TTL)
+    }
+}
+
+impl Games {
+    pub fn new(ttl: Duration) -> Self {
+        Games {
+            default: Arc::new(RwLock::new(Game::new(GameRules::default()))),
+            games: Mutex::default(),
+            ttl,
+            capacity: DEFAULT_GAME_CAPACITY,
+        }
+    }
+
+    pub fn with_capacity(self, capacity: usize) -> Self {
+        Games { capacity, ..self }
+    }
+
+    pub fn default_game(&self) -> SharedGame {
+        self.default.clone()
+    }
+
+    pub fn create(&self, rules: GameRules) -> Uuid {
+        let mut games = self.games.lock();
+        Self::evict_idle(&mut games, self.ttl);
+        while games.len() >= self.capacity {
+            let Some(&oldest) = games
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(id, _)| id)
+            else {
+                break;
+            };
+            games.remove(&oldest);
+        }
+        let id = Uuid::new_v4();
+        let game = Arc::new(RwLock::new(Game::new(rules)));
+        games.insert(id, (game, Instant::now()));
+        id
+    }
+
+    /// Looks up a game, keeping it alive for another TTL.
+    pub fn get(&self, id: Uuid) -> Option<SharedGame> {
+        let mut games = self.games.lock();
+        Self::evict_idle(&mut games, self.ttl);
+        let (game, last_used) = games.get_mut(&id)?;
+        *last_used = Instant::now();
+        Some(game.clone())
+    }
+
+    fn evict_idle(games: &mut HashMap<Uuid, (SharedGame, Instant)>, ttl: Duration) {
+        games.retain(|_, (_, last_used)| last_used.elapsed() < ttl);
+    }
+}
+
+/// The game a request plays: the one named by the `id` path parameter, or
+/// the default game on routes without one.
+pub struct CurrentGame(SharedGame);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for CurrentGame {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        // Routes without any path parameters reject `Path`, hence the option.
+        let params = Option::<Path<HashMap<String, String>>>::from_request_parts(parts, state)
+            .await
+            .unwrap_or_default();
+        let Some(id) = params.and_then(|Path(params)| params.get("id").cloned()) else {
+            return Ok(CurrentGame(state.games.default_game()));
+        };
+        let id = Uuid::parse_str(&id)
+            .map_err(|_| AppError::BadRequest(format!("Invalid game id: {id}")))?;
+        state
+            .games
+            .get(id)
+            .map(CurrentGame)
+            .ok_or_else(|| AppError::NotFound("Game not found".into()))
+    }
+}
+
+#[derive(Serialize)]
+pub struct GameBody {
+    id: Uuid,
+    #[serde(flatten)]
+    rules: GameRules,
+}
+
+#[derive(Deserialize)]
+pub struct PlaceParams {
+    team: String,
+    column: usize,
+}
+
+#[derive(Deserialize)]
+pub struct TeamParams {
+    team: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct AiParams {
+    /// Overrides the game's `ai_depth`.
+    #[validate(range(min = 1, max = 10, message = "must be 1 to 10"))]
+    depth: Option<usize>,
+    /// Overrides the game's `ai_time_ms`.
+    #[validate(range(min = 1, max = 5000, message = "must be 1 to 5000"))]
+    time_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct ReplayParams {
+    moves: usize,
+}
+
+/// The board for bots and frontends, sent instead of the emoji rendering to
+/// clients that accept `application/json`.
+#[derive(Serialize)]
+pub struct BoardBody {
+    /// Rows top first, `null` for empty cells.
+    grid: Vec<Vec<Option<Team>>>,
+    winner: Option<Team>,
+    draw: bool,
+    /// Only set in strict mode while the game is on.
+    next_turn: Option<Team>,
+    /// 1-based columns with room left, none once the game is over.
+    legal_columns: Vec<usize>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryBody {
+    moves: Vec<Move>,
+    notation: String,
+}
+
+/// Creates a game played by the [`GameRules`] in the body, or by the
+/// default rules when the body is empty.
+pub async fn create_game(
+    State(state): State<Arc<AppState>>,
+    body: Bytes,
+) -> Result<impl IntoResponse, AppError> {
+    let rules = if body.is_empty() {
+        GameRules::default()
+    } else {
+        Json::<GameRules>::from_bytes(&body)?.0
+    };
+    rules.check()?;
+    let id = state.games.create(rules);
+
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, format!("/12/games/{id}/board"))],
+        Json(GameBody { id, rules }),
+    ))
+}
+
+/// The media ranges in the `Accept` headers with their quality values,
+/// skipping ranges whose `q` isn't one.
+fn accepted(headers: &HeaderMap) -> Vec<(&str, f32)> {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let media_range = parts.next().unwrap_or_default().trim();
+            let quality = parts
+                .filter_map(|param| param.split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+                .map_or(Some(1.0), |(_, q)| q.trim().parse().ok())
+                .filter(|q| (0.0..=1.0).contains(q))?;
+            Some((media_range, quality))
+        })
+        .collect()
 }
 
-pub async fn random(State(state): State<Arc<AppState>>) -> String {
-    let random_board = Board::gen_random(&mut state.rand.lock());
-    *state.board.write() = random_board;
-    state.board.read().print_result()
+/// Whether the client would rather have a [`BoardBody`] than emoji: it has
+/// to name JSON and weigh it at least as much as plain text.
+fn wants_json(headers: &HeaderMap) -> bool {
+    let accepted = accepted(headers);
+    // The most specific range decides, as in RFC 9110.
+    let quality = |ranges: &[&str]| {
+        ranges.iter().find_map(|range| {
+            accepted
+                .iter()
+                .find(|(accepted, _)| accepted.eq_ignore_ascii_case(range))
+                .map(|&(_, quality)| quality)
+        })
+    };
+    let Some(json) = quality(&["application/json"]) else {
+        return false;
+    };
+    let text = quality(&["text/plain", "text/*", "*/*"]).unwrap_or(0.0);
+    json > 0.0 && json >= text
+}
+
+pub async fn current_board(CurrentGame(game): CurrentGame, headers: HeaderMap) -> Response {
+    game.read().show(StatusCode::OK, wants_json(&headers))
+}
+
+pub async fn reset_board(CurrentGame(game): CurrentGame) -> String {
+    let mut game = game.write();
+    *game = Game::new(game.rules());
+    game.board.to_string()
+}
+
+pub async fn place_item(
+    CurrentGame(game): CurrentGame,
+    path: Result<Path<PlaceParams>, PathRejection>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let Path(PlaceParams { team, column }) = path?;
+    let team = Team::parse(&team)?;
+    let json = wants_json(&headers);
+
+    let response = {
+        let game = game.upgradable_read();
+        let column = match game.check_move(team, column) {
+            Ok(column) => column,
+            Err(illegal) => return refuse(&game, team, illegal, json),
+        };
+        let mut game = RwLockUpgradableReadGuard::upgrade(game);
+        game.apply(team, column);
+        game.response(team)
+    };
+    answer(&game, response).await?;
+    Ok(game.read().show(StatusCode::OK, json))
+}
+
+/// Plays `team`'s move as the AI would, within the `depth` and `time_ms`
+/// given or else the game's.
+pub async fn ai_move(
+    CurrentGame(game): CurrentGame,
+    path: Result<Path<TeamParams>, PathRejection>,
+    params: Result<Query<AiParams>, QueryRejection>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let Path(TeamParams { team }) = path?;
+    let team = Team::parse(&team)?;
+    let Query(params) = params?;
+    params.validate()?;
+    let json = wants_json(&headers);
+
+    let search = {
+        let game = game.read();
+        let rules = game.rules();
+        let depth = params.depth.unwrap_or(rules.ai_depth);
+        let budget = Duration::from_millis(params.time_ms.unwrap_or(rules.ai_time_ms));
+        match game.search(team, depth, budget) {
+            Ok(search) => search,
+            Err(illegal) => return refuse(&game, team, illegal, json),
+        }
+    };
+    let column = search.run().await?;
+    let response = {
+        let mut game = game.write();
+        if !game.unchanged_since(&search) {
+            return Err(AppError::Conflict(
+                "The game changed while the AI was thinking".into(),
+            ));
+        }
+        game.apply(team, column);
+        game.response(team)
+    };
+    answer(&game, response).await?;
+    Ok(game.read().show(StatusCode::OK, json))
+}
+
+/// Answers a move that can't be played.
+fn refuse(game: &Game, team: Team, illegal: IllegalMove, json: bool) -> Result<Response, AppError> {
+    match illegal {
+        IllegalMove::NoSuchColumn => {
+            let width = game.rules().width;
+            Err(AppError::BadRequest(format!(
+                "Column must be between 1 and {width}"
+            )))
+        }
+        // A full column or a finished game is part of the game itself, so
+        // the board is rendered back instead of an error document.
+        IllegalMove::ColumnFull | IllegalMove::GameOver if json => {
+            Ok(game.show(StatusCode::SERVICE_UNAVAILABLE, true))
+        }
+        // As text, only the result, without whose move it is.
+        IllegalMove::ColumnFull | IllegalMove::GameOver => Ok((
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::VARY, "accept")],
+            game.board.print_result(),
+        )
+            .into_response()),
+        IllegalMove::OutOfTurn { turn } => Err(AppError::Conflict(format!(
+            "It is {}'s turn, not {}'s",
+            turn.name(),
+            team.name()
+        ))),
+    }
+}
+
+pub async fn random(CurrentGame(game): CurrentGame) -> String {
+    let mut game = game.write();
+    let rules = game.rules();
+    game.board = Board::gen_random(rules, &mut game.rand);
+    // A fresh position, so play restarts with the first team.
+    game.turn = rules.first;
+    game.moves.clear();
+    game.board.print_result()
+}
+
+pub async fn history(CurrentGame(game): CurrentGame) -> Json<HistoryBody> {
+    let game = game.read();
+    Json(HistoryBody {
+        moves: game.moves.clone(),
+        notation: notation(&game.moves),
+    })
+}
+
+pub async fn undo_move(CurrentGame(game): CurrentGame) -> Result<String, AppError> {
+    let mut game = game.write();
+    game.undo()
+        .ok_or_else(|| AppError::Conflict("No moves to undo".into()))?;
+    Ok(game.render())
+}
+
+/// The board after the first `moves` moves.
+pub async fn replay(
+    CurrentGame(game): CurrentGame,
+    path: Result<Path<ReplayParams>, PathRejection>,
+) -> Result<String, AppError> {
+    let Path(ReplayParams { moves }) = path?;
+    let game = game.read();
+    if moves > game.moves.len() {
+        return Err(AppError::NotFound(format!(
+            "The game has only {} moves",
+            game.moves.len()
+        )));
+    }
+    Ok(game.board_after(moves).print_result())
+}
+
+pub async fn export_moves(CurrentGame(game): CurrentGame) -> String {
+    notation(&game.read().moves)
+}
+
+/// Replaces the game with the moves in the body, played from an empty board
+/// under the game's rules.
+pub async fn import_moves(
+    CurrentGame(game): CurrentGame,
+    body: Result<String, StringRejection>,
+) -> Result<String, AppError> {
+    let body = body?;
+    let invalid = |message: String| {
+        AppError::UnprocessableEntity(vec![InvalidField {
+            field: "moves".into(),
+            message,
+        }])
+    };
+    let moves = parse_notation(&body).map_err(invalid)?;
+    // Importing nothing would reset the game, which takes an admin.
+    if moves.is_empty() {
+        return Err(invalid("no moves to import".into()));
+    }
+
+    let mut game = game.write();
+    let mut imported = Game::new(game.rules());
+    for (i, (team, column)) in moves.into_iter().enumerate() {
+        let column = imported.check_move(team, column).map_err(|illegal| {
+            let reason = match illegal {
+                IllegalMove::NoSuchColumn => format!("there is no column {column}"),
+                IllegalMove::ColumnFull => format!("column {column} is full"),
+                IllegalMove::GameOver => "the game is over".to_string(),
+                IllegalMove::OutOfTurn { turn } => format!("it is {}'s turn", turn.name()),
+            };
+            invalid(format!("move {}: {reason}", i + 1))
+        })?;
+        imported.apply(team, column);
+    }
+    game.board = imported.board;
+    game.turn = imported.turn;
+    game.moves = imported.moves;
+    Ok(game.render())
 }
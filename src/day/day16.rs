@@ -1,41 +1,34 @@
-use axum::{
-    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
-    response::IntoResponse,
-    Json,
-};
+use axum::{extract::rejection::JsonRejection, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::{cookie::Cookie, CookieJar};
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
 use serde_json::Value;
 
+use crate::error::AppError;
+
 pub async fn wrap_present(
-    header: HeaderMap,
     jar: CookieJar,
-    Json(payload): Json<Value>,
-) -> impl IntoResponse {
-    let content_type = header
-        .get(CONTENT_TYPE)
-        .expect("Content-type isn't specified.")
-        .to_str()
-        .expect("content-type isn't parsable to string.");
-    assert_eq!(content_type, "application/json");
+    payload: Result<Json<Value>, JsonRejection>,
+) -> Result<impl IntoResponse, AppError> {
+    // The `Json` extractor already rejects anything but `application/json`.
+    let Json(payload) = payload?;
 
     let jwt = jsonwebtoken::encode(
         &Header::default(),
         &payload,
         &EncodingKey::from_secret(b"secret_key"),
     )
-    .unwrap();
-    (StatusCode::OK, jar.add(Cookie::new("gift", jwt)))
+    .map_err(|err| AppError::Internal(format!("Failed to wrap present: {err}")))?;
+    Ok((StatusCode::OK, jar.add(Cookie::new("gift", jwt))))
 }
 
-pub async fn unwrap_present(jar: CookieJar) -> Result<Json<Value>, StatusCode> {
+pub async fn unwrap_present(jar: CookieJar) -> Result<Json<Value>, AppError> {
     let Some(gift) = jar.get("gift") else {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(AppError::BadRequest("gift cookie is not set".into()));
     };
     let jwt = gift.value();
     let mut validation = Validation::default();
     validation.required_spec_claims.remove("exp");
-    let decoded =
-        jsonwebtoken::decode(jwt, &DecodingKey::from_secret(b"secret_key"), &validation).unwrap();
+    let decoded = jsonwebtoken::decode(jwt, &DecodingKey::from_secret(b"secret_key"), &validation)
+        .map_err(|err| AppError::BadRequest(format!("Invalid gift: {err}")))?;
     Ok(Json(decoded.claims))
 }
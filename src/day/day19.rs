@@ -1,139 +1,1285 @@
-use std::sync::Arc;
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use axum::{
-    extract::{Path, State},
+    body::Body,
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection, StringRejection},
+        Path, Query, State,
+    },
     http::{header, HeaderMap, StatusCode},
-    response::IntoResponse,
-    Json,
+    response::{
+        sse::{Event, KeepAlive},
+        IntoResponse, Sse,
+    },
+    BoxError, Json,
 };
 use axum_macros::debug_handler;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use futures::{stream, StreamExt};
+use parking_lot::Mutex;
+use rand::{distributions::Alphanumeric, rngs::StdRng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
 use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::{
+    error::{AppError, InvalidField},
+    store::{
+        normalize_tag, AuditEntry, AuditQuery, AuthorStats, Quote, QuoteEvent, QuoteVersion,
+        RatedQuote, Rating, SearchHit, SearchQuery, TagCount, TagFilter, TagMatch, Vote,
+    },
+    AppState,
+};
 
-use crate::AppState;
+/// Largest request body accepted by [`draft`] and [`undo_by_id`].
+pub const PAYLOAD_LIMIT: usize = 16 * 1024;
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Validate)]
 pub struct Payload {
+    #[validate(
+        length(min = 1, max = 200, message = "must be 1 to 200 characters"),
+        custom(function = "plain_text")
+    )]
     author: String,
+    #[validate(
+        length(min = 1, max = 2000, message = "must be 1 to 2000 characters"),
+        custom(function = "plain_text")
+    )]
     quote: String,
 }
 
-#[derive(Serialize)]
+/// Also the row format of [`export`] and [`import`], hence the validation.
+#[derive(Serialize, Deserialize, Validate)]
 pub struct ResponseBody {
     id: Uuid,
+    #[validate(
+        length(min = 1, max = 200, message = "must be 1 to 200 characters"),
+        custom(function = "plain_text")
+    )]
     author: String,
+    #[validate(
+        length(min = 1, max = 2000, message = "must be 1 to 2000 characters"),
+        custom(function = "plain_text")
+    )]
     quote: String,
     created_at: DateTime<Utc>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    version: i32,
+    /// Only filled in by reads; votes are neither exported nor imported.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    rating: Option<RatingBody>,
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct RatingBody {
+    upvotes: i64,
+    downvotes: i64,
+    /// Wilson lower bound the top list is ranked by.
+    score: f64,
+}
+
+impl From<Rating> for RatingBody {
+    fn from(value: Rating) -> Self {
+        RatingBody {
+            upvotes: value.upvotes,
+            downvotes: value.downvotes,
+            score: value.score(),
+        }
+    }
+}
+
+impl From<RatedQuote> for ResponseBody {
+    fn from(value: RatedQuote) -> Self {
+        ResponseBody {
+            rating: Some(RatingBody::from(value.rating)),
+            ..ResponseBody::from(value.quote)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct VoteBody {
+    vote: Vote,
+}
+
+/// Rejects surrounding whitespace and control characters other than line
+/// breaks and tabs inside the text.
+fn plain_text(value: &str) -> Result<(), ValidationError> {
+    if value.trim() != value {
+        return Err(ValidationError::new("trimmed")
+            .with_message("must not start or end with whitespace".into()));
+    }
+    if value
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\n' | '\t'))
+    {
+        return Err(ValidationError::new("control_characters")
+            .with_message("must not contain control characters".into()));
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct VersionBody {
+    id: Uuid,
     version: i32,
+    author: String,
+    quote: String,
+    created_at: DateTime<Utc>,
+}
+
+impl From<QuoteVersion> for VersionBody {
+    fn from(value: QuoteVersion) -> Self {
+        VersionBody {
+            id: value.quote_id,
+            version: value.version,
+            author: value.author,
+            quote: value.quote,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct AuthorBody {
+    id: Uuid,
+    name: String,
+    quote_count: i64,
+    first_quote_at: DateTime<Utc>,
+    last_quote_at: DateTime<Utc>,
+}
+
+impl From<AuthorStats> for AuthorBody {
+    fn from(value: AuthorStats) -> Self {
+        AuthorBody {
+            id: value.id,
+            name: value.name,
+            quote_count: value.quote_count,
+            first_quote_at: value.first_quote_at,
+            last_quote_at: value.last_quote_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TagBody {
+    name: String,
+    quote_count: i64,
+}
+
+impl From<TagCount> for TagBody {
+    fn from(value: TagCount) -> Self {
+        TagBody {
+            name: value.name,
+            quote_count: value.quote_count,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TopParams {
+    page: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct TopResponse {
+    quotes: Vec<ResponseBody>,
+    page: i64,
+    next_page: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct QuoteTags {
+    id: Uuid,
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AuditParams {
+    quote_id: Option<Uuid>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    page: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct AuditBody {
+    id: i64,
+    action: String,
+    quote_id: Option<Uuid>,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+    actor: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<AuditEntry> for AuditBody {
+    fn from(value: AuditEntry) -> Self {
+        AuditBody {
+            id: value.id,
+            action: value.record.action,
+            quote_id: value.record.quote_id,
+            before: value.record.before,
+            after: value.record.after,
+            actor: value.record.actor,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct AuditResponse {
+    entries: Vec<AuditBody>,
+    page: i64,
+    next_page: Option<i64>,
+}
+
+/// Header naming the caller in the audit log.
+pub const ACTOR_HEADER: &str = "x-actor";
+/// Sent by reconnecting `EventSource`s with the id of the last event seen.
+const LAST_EVENT_ID: &str = "last-event-id";
+
+const PAGE_SIZE: i64 = 3;
+const AUDIT_PAGE_SIZE: i64 = 50;
+const MAX_TAG_LENGTH: usize = 50;
+const SEARCH_PAGE_SIZE: i64 = 10;
+const TOP_PAGE_SIZE: i64 = 10;
+const MAX_VOTER_LENGTH: usize = 100;
+const TOKEN_LENGTH: usize = 16;
+const TOKEN_TTL: Duration = Duration::from_secs(10 * 60);
+const EXPORT_BATCH_SIZE: i64 = 100;
+const CSV_HEADER: &str = "id,author,quote,created_at,version\n";
+
+/// Continuation tokens handed out by [`list`], mapped to the page they open
+/// and the tag filter of the listing.
+///
+/// Tokens are single use and expire after [`TOKEN_TTL`].
+#[derive(Debug, Default)]
+pub struct ListTokens {
+    tokens: Mutex<HashMap<String, (i64, TagFilter, Instant)>>,
+}
+
+impl ListTokens {
+    fn issue(&self, page: i64, tags: TagFilter) -> String {
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        let mut tokens = self.tokens.lock();
+        tokens.retain(|_, (_, _, issued_at)| issued_at.elapsed() < TOKEN_TTL);
+        tokens.insert(token.clone(), (page, tags, Instant::now()));
+        token
+    }
+
+    fn redeem(&self, token: &str) -> Option<(i64, TagFilter)> {
+        self.tokens
+            .lock()
+            .remove(token)
+            .filter(|(_, _, issued_at)| issued_at.elapsed() < TOKEN_TTL)
+            .map(|(page, tags, _)| (page, tags))
+    }
+}
+
+/// Parses comma separated `tags`; `mode` says whether quotes need all or
+/// any of them.
+fn tag_filter(tags: Option<&str>, mode: TagMatch) -> Result<TagFilter, AppError> {
+    let mut tags = tags
+        .into_iter()
+        .flat_map(|tags| tags.split(','))
+        .map(tag_name)
+        .collect::<Result<Vec<_>, _>>()?;
+    tags.sort();
+    tags.dedup();
+    Ok(TagFilter { tags, mode })
+}
+
+/// Normalizes a tag, rejecting anything but letters, digits, `-` and `_`.
+fn tag_name(raw: &str) -> Result<String, AppError> {
+    let tag = normalize_tag(raw);
+    let valid = (1..=MAX_TAG_LENGTH).contains(&tag.chars().count())
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_'));
+    if !valid {
+        return Err(AppError::UnprocessableEntity(vec![InvalidField {
+            field: "tag".into(),
+            message: format!(
+                "must be 1 to {MAX_TAG_LENGTH} letters, digits, dashes or underscores"
+            ),
+        }]));
+    }
+    Ok(tag)
+}
+
+#[derive(Deserialize)]
+pub struct ListParams {
+    token: Option<String>,
+    tags: Option<String>,
+    #[serde(rename = "match", default)]
+    mode: TagMatch,
+}
+
+#[derive(Serialize)]
+pub struct ListResponse {
+    quotes: Vec<ResponseBody>,
+    page: i64,
+    next_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SearchParams {
+    q: Option<String>,
+    author: Option<String>,
+    page: Option<i64>,
+    tags: Option<String>,
+    #[serde(rename = "match", default)]
+    mode: TagMatch,
+}
+
+#[derive(Serialize)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    quote: ResponseBody,
+    rank: f32,
+    snippet: String,
+}
+
+impl SearchResult {
+    fn new(hit: SearchHit, rating: Rating) -> Self {
+        SearchResult {
+            quote: ResponseBody::from(RatedQuote {
+                quote: hit.quote,
+                rating,
+            }),
+            rank: hit.rank,
+            snippet: hit.snippet,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SearchResponse {
+    results: Vec<SearchResult>,
+    page: i64,
+    next_page: Option<i64>,
+}
+
+/// Row format for [`export`] and [`import`]; rows are [`ResponseBody`]s.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferFormat {
+    #[default]
+    Jsonl,
+    Csv,
+}
+
+impl TransferFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            TransferFormat::Jsonl => "application/x-ndjson",
+            TransferFormat::Csv => "text/csv",
+        }
+    }
+
+    fn content_disposition(self) -> &'static str {
+        match self {
+            TransferFormat::Jsonl => "attachment; filename=\"quotes.jsonl\"",
+            TransferFormat::Csv => "attachment; filename=\"quotes.csv\"",
+        }
+    }
+
+    /// Serializes quotes as rows. CSV output has no header.
+    fn encode(self, quotes: Vec<Quote>) -> Result<Vec<u8>, BoxError> {
+        let mut rows = Vec::new();
+        match self {
+            TransferFormat::Jsonl => {
+                for quote in quotes {
+                    serde_json::to_writer(&mut rows, &ResponseBody::from(quote))?;
+                    rows.push(b'\n');
+                }
+            }
+            TransferFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(&mut rows);
+                for quote in quotes {
+                    writer.serialize(ResponseBody::from(quote))?;
+                }
+                writer.flush()?;
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Parses rows, each paired with its 1-based line number.
+    fn decode(self, body: &str) -> Vec<(u64, Result<ResponseBody, String>)> {
+        match self {
+            TransferFormat::Jsonl => body
+                .lines()
+                .zip(1..)
+                .filter(|(line, _)| !line.trim().is_empty())
+                .map(|(line, number)| {
+                    (
+                        number,
+                        serde_json::from_str(line).map_err(|e| e.to_string()),
+                    )
+                })
+                .collect(),
+            TransferFormat::Csv => {
+                let mut reader = csv::Reader::from_reader(body.as_bytes());
+                let headers = match reader.headers() {
+                    Ok(headers) => headers.clone(),
+                    Err(err) => return vec![(1, Err(err.to_string()))],
+                };
+                reader
+                    .records()
+                    .map(|record| match record {
+                        Ok(record) => (
+                            record.position().map_or(0, |p| p.line()),
+                            record
+                                .deserialize(Some(&headers))
+                                .map_err(|e| e.to_string()),
+                        ),
+                        Err(err) => (err.position().map_or(0, |p| p.line()), Err(err.to_string())),
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TransferParams {
+    #[serde(default)]
+    format: TransferFormat,
+}
+
+#[derive(Serialize)]
+pub struct ImportError {
+    line: u64,
+    message: String,
+}
+
+#[derive(Serialize)]
+pub struct ImportReport {
+    imported: usize,
+    errors: Vec<ImportError>,
+}
+
+fn validate_row(row: &ResponseBody) -> Result<(), String> {
+    row.validate().map_err(|errors| {
+        InvalidField::from_errors(&errors)
+            .into_iter()
+            .map(|field| format!("{}: {}", field.field, field.message))
+            .collect::<Vec<_>>()
+            .join("; ")
+    })
+}
+
+impl From<ResponseBody> for Quote {
+    fn from(value: ResponseBody) -> Self {
+        Quote {
+            id: value.id,
+            author: value.author,
+            quote: value.quote,
+            created_at: value.created_at,
+            version: value.version,
+            deleted_at: None,
+        }
+    }
+}
+
+impl From<Quote> for ResponseBody {
+    fn from(value: Quote) -> Self {
+        ResponseBody {
+            id: value.id,
+            author: value.author,
+            quote: value.quote,
+            created_at: value.created_at,
+            version: value.version,
+            rating: None,
+        }
+    }
+}
+
+/// Pairs quotes with their current ratings.
+async fn rated(state: &AppState, quotes: Vec<Quote>) -> Result<Vec<RatedQuote>, AppError> {
+    let ids: Vec<Uuid> = quotes.iter().map(|quote| quote.id).collect();
+    let ratings = state.quotes.ratings(&ids).await?;
+    Ok(quotes
+        .into_iter()
+        .map(|quote| RatedQuote {
+            rating: ratings.get(&quote.id).copied().unwrap_or_default(),
+            quote,
+        })
+        .collect())
+}
+
+async fn rated_body(state: &AppState, quote: Quote) -> Result<ResponseBody, AppError> {
+    let mut ratings = state.quotes.ratings(&[quote.id]).await?;
+    let rating = ratings.remove(&quote.id).unwrap_or_default();
+    Ok(ResponseBody::from(RatedQuote { quote, rating }))
+}
+
+/// Rejects voter ids that are empty, too long or not plain text.
+fn voter_id(voter: &str) -> Result<(), AppError> {
+    let message = if !(1..=MAX_VOTER_LENGTH).contains(&voter.chars().count()) {
+        Some(format!("must be 1 to {MAX_VOTER_LENGTH} characters"))
+    } else {
+        plain_text(voter)
+            .err()
+            .and_then(|error| error.message)
+            .map(|message| message.to_string())
+    };
+    match message {
+        Some(message) => Err(AppError::UnprocessableEntity(vec![InvalidField {
+            field: "voter".into(),
+            message,
+        }])),
+        None => Ok(()),
+    }
+}
+
+fn quote_not_found() -> AppError {
+    AppError::NotFound("Quote not found".into())
+}
+
+fn deleted_quote_not_found() -> AppError {
+    AppError::NotFound("Deleted quote not found".into())
+}
+
+fn quote_modified() -> AppError {
+    AppError::PreconditionFailed("Quote has been modified".into())
+}
+
+/// Strong entity tag for the current version of a quote.
+fn etag(quote: &Quote) -> String {
+    format!("\"{}-{}\"", quote.id, quote.version)
+}
+
+/// Evaluates `If-Match` against the current quote and returns the version a
+/// write must apply to, so concurrent edits in between are caught too.
+async fn precondition(
+    state: &AppState,
+    headers: &HeaderMap,
+    id: Uuid,
+) -> Result<Option<i32>, AppError> {
+    let Some(if_match) = if_match(state, headers)? else {
+        return Ok(None);
+    };
+    let current = state.quotes.get(id).await?.ok_or_else(quote_not_found)?;
+    matches(if_match, &current).map(Some)
+}
+
+/// Like [`precondition`], for writes to a soft-deleted quote.
+async fn deleted_precondition(
+    state: &AppState,
+    headers: &HeaderMap,
+    id: Uuid,
+) -> Result<Option<i32>, AppError> {
+    let Some(if_match) = if_match(state, headers)? else {
+        return Ok(None);
+    };
+    let current = state
+        .quotes
+        .get_deleted(id)
+        .await?
+        .ok_or_else(deleted_quote_not_found)?;
+    matches(if_match, &current).map(Some)
+}
+
+/// The `If-Match` header, if there is one and it is allowed to be missing.
+fn if_match<'a>(state: &AppState, headers: &'a HeaderMap) -> Result<Option<&'a str>, AppError> {
+    let Some(if_match) = headers.get(header::IF_MATCH) else {
+        if state.require_if_match {
+            return Err(AppError::PreconditionRequired(
+                "If-Match header is required".into(),
+            ));
+        }
+        return Ok(None);
+    };
+    if_match
+        .to_str()
+        .map(Some)
+        .map_err(|_| AppError::BadRequest("If-Match is not parsable to string".into()))
+}
+
+/// Checks `if_match` against `current` and returns its version.
+fn matches(if_match: &str, current: &Quote) -> Result<i32, AppError> {
+    let current_etag = etag(current);
+    if !if_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag == current_etag)
+    {
+        return Err(quote_modified());
+    }
+    Ok(current.version)
+}
+
+fn actor(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(ACTOR_HEADER)
+        .and_then(|actor| actor.to_str().ok())
+        .map(str::trim)
+        .filter(|actor| !actor.is_empty())
+        .map(str::to_string)
 }
 
 #[debug_handler]
 pub async fn draft(
-    header: HeaderMap,
+    headers: HeaderMap,
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<Payload>,
-) -> impl IntoResponse {
-    let content_type = header
-        .get(header::CONTENT_TYPE)
-        .expect("Content-type isn't specified.")
-        .to_str()
-        .expect("content-type isn't parsable to string.");
-    assert_eq!(content_type, "application/json");
-
+    payload: Result<Json<Payload>, JsonRejection>,
+) -> Result<impl IntoResponse, AppError> {
+    let Json(payload) = payload?;
+    payload.validate()?;
     let uuid4 = Uuid::new_v4();
 
-    let result = sqlx::query!(
-        "INSERT INTO quotes (id, author, quote) VALUES ($1, $2, $3) RETURNING *",
-        uuid4,
-        payload.author,
-        payload.quote
-    )
-    .fetch_one(&state.db)
-    .await
-    .unwrap();
-
-    (
+    let result = state
+        .quotes
+        .insert(
+            uuid4,
+            &payload.author,
+            &payload.quote,
+            actor(&headers).as_deref(),
+        )
+        .await?;
+
+    Ok((
         StatusCode::CREATED,
-        Json(ResponseBody {
-            id: result.id,
-            author: result.author,
-            quote: result.quote,
-            created_at: result.created_at,
-            version: result.version,
-        }),
-    )
+        [(header::ETAG, etag(&result))],
+        Json(ResponseBody::from(result)),
+    ))
 }
 
-pub async fn reset(State(state): State<Arc<AppState>>) -> StatusCode {
-    sqlx::query!("TRUNCATE quotes")
-        .execute(&state.db)
-        .await
-        .unwrap();
+pub async fn reset(
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Result<StatusCode, AppError> {
+    state.quotes.reset(actor(&headers).as_deref()).await?;
 
-    StatusCode::OK
+    Ok(StatusCode::OK)
 }
 
 pub async fn cite_by_id(
-    Path(id): Path<Uuid>,
+    id: Result<Path<Uuid>, PathRejection>,
     State(state): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let result = sqlx::query!("SELECT * from quotes where id=$1", id)
-        .fetch_one(&state.db)
-        .await
-        .map_err(|_| StatusCode::NOT_FOUND)?;
+) -> Result<impl IntoResponse, AppError> {
+    let Path(id) = id?;
+    let result = state.quotes.get(id).await?.ok_or_else(quote_not_found)?;
+    let etag = etag(&result);
 
     Ok((
         StatusCode::OK,
-        Json(ResponseBody {
-            id: result.id,
-            author: result.author,
-            quote: result.quote,
-            created_at: result.created_at,
-            version: result.version,
-        }),
+        [(header::ETAG, etag)],
+        Json(rated_body(&state, result).await?),
     ))
 }
 
 pub async fn remove_by_id(
-    Path(id): Path<Uuid>,
+    id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Path(id) = id?;
+    let expected_version = precondition(&state, &headers, id).await?;
+    let result = state
+        .quotes
+        .remove(id, expected_version, actor(&headers).as_deref())
+        .await?
+        .ok_or_else(|| match expected_version {
+            Some(_) => quote_modified(),
+            None => quote_not_found(),
+        })?;
+
+    Ok((StatusCode::OK, Json(ResponseBody::from(result))))
+}
+
+pub async fn undo_by_id(
+    id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
     State(state): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let result = sqlx::query!("DELETE FROM quotes WHERE id = $1 RETURNING *", id)
-        .fetch_one(&state.db)
-        .await
-        .map_err(|_| StatusCode::NOT_FOUND)?;
+    payload: Result<Json<Payload>, JsonRejection>,
+) -> Result<impl IntoResponse, AppError> {
+    let Path(id) = id?;
+    let Json(payload) = payload?;
+    payload.validate()?;
+    let expected_version = precondition(&state, &headers, id).await?;
+    let result = state
+        .quotes
+        .update(
+            id,
+            &payload.author,
+            &payload.quote,
+            expected_version,
+            actor(&headers).as_deref(),
+        )
+        .await?
+        .ok_or_else(|| match expected_version {
+            Some(_) => quote_modified(),
+            None => quote_not_found(),
+        })?;
 
     Ok((
         StatusCode::OK,
-        Json(ResponseBody {
-            id: result.id,
-            author: result.author,
-            quote: result.quote,
-            created_at: result.created_at,
-            version: result.version,
-        }),
+        [(header::ETAG, etag(&result))],
+        Json(ResponseBody::from(result)),
     ))
 }
 
-pub async fn undo_by_id(
-    Path(id): Path<Uuid>,
+pub async fn list(
+    params: Result<Query<ListParams>, QueryRejection>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Query(params) = params?;
+    // Tokens carry the filter of the listing they continue.
+    let (page, tags) = match params.token {
+        Some(token) => state
+            .list_tokens
+            .redeem(&token)
+            .ok_or_else(|| AppError::BadRequest("Unknown or stale token".into()))?,
+        None => (1, tag_filter(params.tags.as_deref(), params.mode)?),
+    };
+
+    // Fetch one extra row to find out whether another page follows.
+    let mut quotes = state
+        .quotes
+        .list((page - 1) * PAGE_SIZE, PAGE_SIZE + 1, &tags)
+        .await?;
+    let next_token = if quotes.len() as i64 > PAGE_SIZE {
+        quotes.truncate(PAGE_SIZE as usize);
+        Some(state.list_tokens.issue(page + 1, tags))
+    } else {
+        None
+    };
+
+    Ok(Json(ListResponse {
+        quotes: rated(&state, quotes)
+            .await?
+            .into_iter()
+            .map(ResponseBody::from)
+            .collect(),
+        page,
+        next_token,
+    }))
+}
+
+pub async fn history_by_id(
+    id: Result<Path<Uuid>, PathRejection>,
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<Payload>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let result = sqlx::query!("UPDATE quotes SET (author, quote, version) = ($1, $2, version+1) WHERE id = $3 RETURNING *", payload.author, payload.quote, id)
-        .fetch_one(&state.db)
-        .await
-        .map_err(|_| StatusCode::NOT_FOUND)?;
+) -> Result<impl IntoResponse, AppError> {
+    let Path(id) = id?;
+    let versions = state
+        .quotes
+        .history(id)
+        .await?
+        .ok_or_else(quote_not_found)?;
+
+    Ok(Json(
+        versions
+            .into_iter()
+            .map(VersionBody::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+pub async fn version_by_id(
+    path: Result<Path<(Uuid, i32)>, PathRejection>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Path((id, version)) = path?;
+    let result = state
+        .quotes
+        .version(id, version)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Quote version not found".into()))?;
+
+    Ok(Json(VersionBody::from(result)))
+}
+
+pub async fn revert_by_id(
+    path: Result<Path<(Uuid, i32)>, PathRejection>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Path((id, version)) = path?;
+    let expected_version = precondition(&state, &headers, id).await?;
+    let Some(result) = state
+        .quotes
+        .revert(id, version, expected_version, actor(&headers).as_deref())
+        .await?
+    else {
+        // A missing version takes precedence over a lost race.
+        return Err(match expected_version {
+            Some(_) if state.quotes.version(id, version).await?.is_some() => quote_modified(),
+            _ => AppError::NotFound("Quote version not found".into()),
+        });
+    };
+
+    Ok((
+        StatusCode::OK,
+        [(header::ETAG, etag(&result))],
+        Json(ResponseBody::from(result)),
+    ))
+}
+
+pub async fn restore_by_id(
+    id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Path(id) = id?;
+    let expected_version = deleted_precondition(&state, &headers, id).await?;
+    let result = state
+        .quotes
+        .restore(id, expected_version, actor(&headers).as_deref())
+        .await?
+        .ok_or_else(|| match expected_version {
+            Some(_) => quote_modified(),
+            None => deleted_quote_not_found(),
+        })?;
 
     Ok((
         StatusCode::OK,
-        Json(ResponseBody {
-            id: result.id,
-            author: result.author,
-            quote: result.quote,
-            created_at: result.created_at,
-            version: result.version,
+        [(header::ETAG, etag(&result))],
+        Json(ResponseBody::from(result)),
+    ))
+}
+
+pub async fn purge_by_id(
+    id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Path(id) = id?;
+    let expected_version = deleted_precondition(&state, &headers, id).await?;
+    let result = state
+        .quotes
+        .purge(id, expected_version, actor(&headers).as_deref())
+        .await?
+        .ok_or_else(|| match expected_version {
+            Some(_) => quote_modified(),
+            None => deleted_quote_not_found(),
+        })?;
+
+    Ok((StatusCode::OK, Json(ResponseBody::from(result))))
+}
+
+pub async fn search(
+    params: Result<Query<SearchParams>, QueryRejection>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Query(params) = params?;
+    let tags = tag_filter(params.tags.as_deref(), params.mode)?;
+    let non_blank = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+    let text = non_blank(params.q);
+    let author = non_blank(params.author);
+    if text.is_none() && author.is_none() {
+        return Err(AppError::BadRequest("q or author is required".into()));
+    }
+    let page = params.page.unwrap_or(1);
+    if page < 1 {
+        return Err(AppError::BadRequest("page must be at least 1".into()));
+    }
+
+    // Fetch one extra hit to find out whether another page follows.
+    let mut hits = state
+        .quotes
+        .search(&SearchQuery {
+            text,
+            author,
+            tags,
+            offset: (page - 1) * SEARCH_PAGE_SIZE,
+            limit: SEARCH_PAGE_SIZE + 1,
+        })
+        .await?;
+    let next_page = if hits.len() as i64 > SEARCH_PAGE_SIZE {
+        hits.truncate(SEARCH_PAGE_SIZE as usize);
+        Some(page + 1)
+    } else {
+        None
+    };
+
+    let ids: Vec<Uuid> = hits.iter().map(|hit| hit.quote.id).collect();
+    let ratings = state.quotes.ratings(&ids).await?;
+
+    Ok(Json(SearchResponse {
+        results: hits
+            .into_iter()
+            .map(|hit| {
+                let rating = ratings.get(&hit.quote.id).copied().unwrap_or_default();
+                SearchResult::new(hit, rating)
+            })
+            .collect(),
+        page,
+        next_page,
+    }))
+}
+
+pub async fn authors(State(state): State<Arc<AppState>>) -> Result<impl IntoResponse, AppError> {
+    let authors = state.quotes.authors().await?;
+
+    Ok(Json(
+        authors
+            .into_iter()
+            .map(AuthorBody::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+pub async fn quotes_by_author(
+    id: Result<Path<Uuid>, PathRejection>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Path(id) = id?;
+    let quotes = state
+        .quotes
+        .quotes_by_author(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Author not found".into()))?;
+
+    Ok(Json(
+        rated(&state, quotes)
+            .await?
+            .into_iter()
+            .map(ResponseBody::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// The live quote at `pick(count)` in creation order.
+async fn pick_quote(
+    state: &AppState,
+    pick: impl FnOnce(i64) -> i64,
+) -> Result<ResponseBody, AppError> {
+    let count = state.quotes.count().await?;
+    if count == 0 {
+        return Err(AppError::NotFound("The quote book is empty".into()));
+    }
+    let quote = state
+        .quotes
+        .list(pick(count), 1, &TagFilter::default())
+        .await?
+        .pop()
+        // Quotes removed in between shrink the book.
+        .ok_or_else(quote_not_found)?;
+    rated_body(state, quote).await
+}
+
+pub async fn random_quote(
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let quote = pick_quote(&state, |count| state.quote_rand.lock().gen_range(0..count)).await?;
+
+    Ok(Json(quote))
+}
+
+/// Same quote all UTC day, as long as the book doesn't change.
+pub async fn daily_quote(
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let today = Utc::now().date_naive();
+    let quote = pick_quote(&state, |count| daily_index(state.quote_seed, today, count)).await?;
+
+    Ok(Json(quote))
+}
+
+fn daily_index(seed: u64, date: NaiveDate, count: i64) -> i64 {
+    let day = date.num_days_from_ce() as u64;
+    StdRng::seed_from_u64(seed ^ day).gen_range(0..count)
+}
+
+pub async fn tag_quote(
+    path: Result<Path<(Uuid, String)>, PathRejection>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Path((id, tag)) = path?;
+    let tags = state
+        .quotes
+        .tag(id, &tag_name(&tag)?)
+        .await?
+        .ok_or_else(quote_not_found)?;
+
+    Ok(Json(QuoteTags { id, tags }))
+}
+
+pub async fn untag_quote(
+    path: Result<Path<(Uuid, String)>, PathRejection>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Path((id, tag)) = path?;
+    let tags = state
+        .quotes
+        .untag(id, &tag_name(&tag)?)
+        .await?
+        .ok_or_else(quote_not_found)?;
+
+    Ok(Json(QuoteTags { id, tags }))
+}
+
+pub async fn vote_quote(
+    path: Result<Path<(Uuid, String)>, PathRejection>,
+    State(state): State<Arc<AppState>>,
+    payload: Result<Json<VoteBody>, JsonRejection>,
+) -> Result<impl IntoResponse, AppError> {
+    let Path((id, voter)) = path?;
+    let Json(payload) = payload?;
+    voter_id(&voter)?;
+    let rating = state
+        .quotes
+        .vote(id, &voter, Some(payload.vote))
+        .await?
+        .ok_or_else(quote_not_found)?;
+
+    Ok(Json(RatingBody::from(rating)))
+}
+
+pub async fn unvote_quote(
+    path: Result<Path<(Uuid, String)>, PathRejection>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Path((id, voter)) = path?;
+    voter_id(&voter)?;
+    let rating = state
+        .quotes
+        .vote(id, &voter, None)
+        .await?
+        .ok_or_else(quote_not_found)?;
+
+    Ok(Json(RatingBody::from(rating)))
+}
+
+/// Voted quotes, best rated first.
+pub async fn top(
+    params: Result<Query<TopParams>, QueryRejection>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Query(params) = params?;
+    let page = params.page.unwrap_or(1);
+    if page < 1 {
+        return Err(AppError::BadRequest("page must be at least 1".into()));
+    }
+
+    // Fetch one extra quote to find out whether another page follows.
+    let mut quotes = state
+        .quotes
+        .top((page - 1) * TOP_PAGE_SIZE, TOP_PAGE_SIZE + 1)
+        .await?;
+    let next_page = if quotes.len() as i64 > TOP_PAGE_SIZE {
+        quotes.truncate(TOP_PAGE_SIZE as usize);
+        Some(page + 1)
+    } else {
+        None
+    };
+
+    Ok(Json(TopResponse {
+        quotes: quotes.into_iter().map(ResponseBody::from).collect(),
+        page,
+        next_page,
+    }))
+}
+
+fn sse_event(event: QuoteEvent) -> Result<Event, axum::Error> {
+    Event::default()
+        .id(event.id.to_string())
+        .event(event.kind.as_str())
+        .json_data(ResponseBody::from(event.quote))
+}
+
+/// Streams quote changes as they happen. With `Last-Event-ID` set, the kept
+/// events after it are replayed first.
+pub async fn events(
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let last_id = headers
+        .get(LAST_EVENT_ID)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| value.trim().parse::<i64>().ok())
+                .ok_or_else(|| AppError::BadRequest("Invalid Last-Event-ID".into()))
+        })
+        .transpose()?;
+
+    // Subscribe before catching up so nothing slips through in between;
+    // events sent from the backlog are skipped on the live feed. Others are
+    // passed on even with lower ids, since ids don't follow commit order.
+    // Stores that share events through Postgres catch their feed up after
+    // losing the connection, so it may arrive late but not with gaps.
+    let live = state.quotes.subscribe();
+    let backlog = match last_id {
+        Some(last_id) => state.quotes.events_since(last_id).await?,
+        None => Vec::new(),
+    };
+    let resumed_after = last_id.unwrap_or(0);
+    let sent: HashSet<i64> = backlog.iter().map(|event| event.id).collect();
+
+    let live = stream::unfold((live, sent), move |(mut live, sent)| async move {
+        loop {
+            match live.recv().await {
+                Ok(event) if event.id <= resumed_after || sent.contains(&event.id) => continue,
+                Ok(event) => return Some((event, (live, sent))),
+                // Ending the stream makes the client reconnect and catch up
+                // from the backlog.
+                Err(RecvError::Lagged(_) | RecvError::Closed) => return None,
+            }
+        }
+    });
+    let events = stream::iter(backlog).chain(live).map(sse_event);
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+pub async fn tags(State(state): State<Arc<AppState>>) -> Result<impl IntoResponse, AppError> {
+    let tags = state.quotes.tags().await?;
+
+    Ok(Json(
+        tags.into_iter().map(TagBody::from).collect::<Vec<_>>(),
+    ))
+}
+
+pub async fn export(
+    params: Result<Query<TransferParams>, QueryRejection>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Query(TransferParams { format }) = params?;
+    let quotes = state.quotes.clone();
+
+    // Page through the store so large books are never held in memory at once,
+    // continuing after the last quote seen so concurrent writes can't shift
+    // quotes across batch boundaries.
+    let rows = stream::try_unfold(Some(None), move |after| {
+        let quotes = quotes.clone();
+        async move {
+            let Some(after) = after else {
+                return Ok(None);
+            };
+            let batch = quotes.list_after(after, EXPORT_BATCH_SIZE).await?;
+            let next = (batch.len() as i64 == EXPORT_BATCH_SIZE)
+                .then(|| batch.last().map(|quote| (quote.created_at, quote.id)));
+            Ok::<_, BoxError>(Some((format.encode(batch)?, next)))
+        }
+    });
+    let header = match format {
+        TransferFormat::Jsonl => None,
+        TransferFormat::Csv => Some(Ok(CSV_HEADER.as_bytes().to_vec())),
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type()),
+            (header::CONTENT_DISPOSITION, format.content_disposition()),
+        ],
+        Body::from_stream(stream::iter(header).chain(rows)),
+    ))
+}
+
+/// Imports an export, all or nothing. Any invalid row, duplicate id or id
+/// already in the book fails the whole import with one error per line.
+pub async fn import(
+    params: Result<Query<TransferParams>, QueryRejection>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+    body: Result<String, StringRejection>,
+) -> Result<impl IntoResponse, AppError> {
+    let Query(TransferParams { format }) = params?;
+    let body = body?;
+
+    let mut errors = Vec::new();
+    let mut quotes = Vec::new();
+    let mut lines = HashMap::new();
+    for (line, row) in format.decode(&body) {
+        let row = match row.and_then(|row| validate_row(&row).map(|()| row)) {
+            Ok(row) => row,
+            Err(message) => {
+                errors.push(ImportError { line, message });
+                continue;
+            }
+        };
+        match lines.entry(row.id) {
+            Entry::Occupied(first) => errors.push(ImportError {
+                line,
+                message: format!("Duplicate id, first seen on line {}", first.get()),
+            }),
+            Entry::Vacant(entry) => {
+                entry.insert(line);
+                quotes.push(Quote::from(row));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        errors = state
+            .quotes
+            .import(&quotes, actor(&headers).as_deref())
+            .await?
+            .into_iter()
+            .map(|id| ImportError {
+                line: lines[&id],
+                message: "Quote id already exists".into(),
+            })
+            .collect();
+    }
+    if !errors.is_empty() {
+        errors.sort_by_key(|error| error.line);
+        return Ok((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(ImportReport {
+                imported: 0,
+                errors,
+            }),
+        ));
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(ImportReport {
+            imported: quotes.len(),
+            errors,
         }),
     ))
 }
+
+pub async fn audit_log(
+    params: Result<Query<AuditParams>, QueryRejection>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let Query(params) = params?;
+    let page = params.page.unwrap_or(1);
+    if page < 1 {
+        return Err(AppError::BadRequest("page must be at least 1".into()));
+    }
+
+    // Fetch one extra entry to find out whether another page follows.
+    let mut entries = state
+        .quotes
+        .audit(&AuditQuery {
+            quote_id: params.quote_id,
+            since: params.since,
+            until: params.until,
+            offset: (page - 1) * AUDIT_PAGE_SIZE,
+            limit: AUDIT_PAGE_SIZE + 1,
+        })
+        .await?;
+    let next_page = if entries.len() as i64 > AUDIT_PAGE_SIZE {
+        entries.truncate(AUDIT_PAGE_SIZE as usize);
+        Some(page + 1)
+    } else {
+        None
+    };
+
+    Ok(Json(AuditResponse {
+        entries: entries.into_iter().map(AuditBody::from).collect(),
+        page,
+        next_page,
+    }))
+}
@@ -0,0 +1,195 @@
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection, StringRejection},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use validator::ValidationErrors;
+
+/// Error type shared by every handler.
+///
+/// Rendered as an RFC 9457 problem details document
+/// (`application/problem+json`) with a stable status code per variant.
+#[derive(Debug)]
+pub enum AppError {
+    BadRequest(String),
+    /// Missing or invalid credentials; asks for a bearer token.
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    /// The request clashes with the current state, like a move out of turn.
+    Conflict(String),
+    PreconditionFailed(String),
+    PayloadTooLarge(String),
+    UnsupportedMediaType(String),
+    /// A well-formed payload with invalid values, one entry per problem.
+    UnprocessableEntity(Vec<InvalidField>),
+    PreconditionRequired(String),
+    TooManyRequests(String),
+    Database(sqlx::Error),
+    Internal(String),
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvalidField {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+struct ProblemDetails {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    invalid_fields: Vec<InvalidField>,
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::UnprocessableEntity(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::PreconditionRequired(_) => StatusCode::PRECONDITION_REQUIRED,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Database(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            AppError::BadRequest(detail)
+            | AppError::Unauthorized(detail)
+            | AppError::Forbidden(detail)
+            | AppError::NotFound(detail)
+            | AppError::Conflict(detail)
+            | AppError::PreconditionFailed(detail)
+            | AppError::PayloadTooLarge(detail)
+            | AppError::UnsupportedMediaType(detail)
+            | AppError::PreconditionRequired(detail)
+            | AppError::TooManyRequests(detail)
+            | AppError::Internal(detail) => detail.clone(),
+            AppError::UnprocessableEntity(_) => "Invalid fields".to_string(),
+            // Don't leak database internals to clients.
+            AppError::Database(_) => "Database error".to_string(),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        if let AppError::Database(err) = &self {
+            tracing::error!("database error: {err}");
+        }
+
+        let status = self.status();
+        let detail = self.detail();
+        let challenge = matches!(self, AppError::Unauthorized(_));
+        let invalid_fields = match self {
+            AppError::UnprocessableEntity(fields) => fields,
+            _ => Vec::new(),
+        };
+        let body = ProblemDetails {
+            kind: "about:blank",
+            title: status.canonical_reason().unwrap_or("Unknown error"),
+            status: status.as_u16(),
+            detail,
+            invalid_fields,
+        };
+        let mut response = (
+            status,
+            [(header::CONTENT_TYPE, "application/problem+json")],
+            Json(body),
+        )
+            .into_response();
+        if challenge {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+        }
+        response
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(value: sqlx::Error) -> Self {
+        match value {
+            sqlx::Error::RowNotFound => AppError::NotFound("Resource not found".to_string()),
+            err => AppError::Database(err),
+        }
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(value: JsonRejection) -> Self {
+        match value {
+            JsonRejection::MissingJsonContentType(err) => {
+                AppError::UnsupportedMediaType(err.body_text())
+            }
+            err if err.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                AppError::PayloadTooLarge(err.body_text())
+            }
+            err => AppError::BadRequest(err.body_text()),
+        }
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(value: QueryRejection) -> Self {
+        AppError::BadRequest(value.body_text())
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(value: PathRejection) -> Self {
+        AppError::BadRequest(value.body_text())
+    }
+}
+
+impl From<StringRejection> for AppError {
+    fn from(value: StringRejection) -> Self {
+        match value.status() {
+            StatusCode::PAYLOAD_TOO_LARGE => AppError::PayloadTooLarge(value.body_text()),
+            _ => AppError::BadRequest(value.body_text()),
+        }
+    }
+}
+
+impl InvalidField {
+    /// Flattens validation errors into one entry per failed rule.
+    pub fn from_errors(errors: &ValidationErrors) -> Vec<Self> {
+        let mut fields: Vec<_> = errors
+            .field_errors()
+            .into_iter()
+            .flat_map(|(field, errors)| {
+                errors.iter().map(move |error| InvalidField {
+                    field: field.to_string(),
+                    message: error
+                        .message
+                        .as_ref()
+                        .map_or_else(|| error.code.to_string(), |message| message.to_string()),
+                })
+            })
+            .collect();
+        // Field errors come out of a hash map.
+        fields.sort_by(|a, b| (&a.field, &a.message).cmp(&(&b.field, &b.message)));
+        fields
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(value: ValidationErrors) -> Self {
+        AppError::UnprocessableEntity(InvalidField::from_errors(&value))
+    }
+}
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use auth::{require_admin, AdminAuth};
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post, put},
+    Router,
+};
+
+use day::{
+    day00::{hello_world, with_status_and_array_headers},
+    day02::{extract_ipv4_key, extract_ipv6_key, ipv4_encryption, ipv6_encryption},
+    day05::parse_manifest,
+    day09::{create_bucket, refill_milk, withdraw_milk},
+    day12::{
+        ai_move, create_game, current_board, export_moves, history, import_moves, place_item,
+        random, replay, reset_board, undo_move, Games,
+    },
+    day16::{unwrap_present, wrap_present},
+    day19::{
+        audit_log, authors, cite_by_id, daily_quote, draft, events, export, history_by_id, import,
+        list, purge_by_id, quotes_by_author, random_quote, remove_by_id, reset, restore_by_id,
+        revert_by_id, search, tag_quote, tags, top, undo_by_id, untag_quote, unvote_quote,
+        version_by_id, vote_quote, ListTokens, PAYLOAD_LIMIT,
+    },
+    day23::{ornament, present, star},
+};
+use leaky_bucket::RateLimiter;
+use parking_lot::{Mutex, RwLock};
+use rand::{rngs::StdRng, SeedableRng};
+use store::QuoteStore;
+use tower::ServiceBuilder;
+use tower_http::services::ServeDir;
+
+pub mod auth;
+pub mod day;
+pub mod error;
+pub mod store;
+
+pub const DEFAULT_QUOTE_SEED: u64 = 2024;
+
+#[derive(Debug)]
+pub struct AppState {
+    pub milk_amount: RwLock<RateLimiter>,
+    pub games: Games,
+    pub quotes: Arc<dyn QuoteStore>,
+    pub list_tokens: ListTokens,
+    /// Reject quote updates and deletes that don't send `If-Match`.
+    pub require_if_match: bool,
+    /// Seeds `quote_rand` and picks the quote of the day, so replicas sharing
+    /// it agree on the daily quote.
+    pub quote_seed: u64,
+    pub quote_rand: Mutex<StdRng>,
+    /// Guards the reset and refill routes.
+    pub admin: AdminAuth,
+}
+
+impl AppState {
+    pub fn new(quotes: Arc<dyn QuoteStore>) -> AppState {
+        AppState {
+            milk_amount: RwLock::new(create_bucket()),
+            games: Games::default(),
+            quotes,
+            list_tokens: ListTokens::default(),
+            require_if_match: false,
+            quote_seed: DEFAULT_QUOTE_SEED,
+            quote_rand: Mutex::new(StdRng::seed_from_u64(DEFAULT_QUOTE_SEED)),
+            admin: AdminAuth::default(),
+        }
+    }
+
+    pub fn with_quote_seed(self, seed: u64) -> AppState {
+        AppState {
+            quote_seed: seed,
+            quote_rand: Mutex::new(StdRng::seed_from_u64(seed)),
+            ..self
+        }
+    }
+}
+
+/// Builds the full application router, including the static assets.
+pub fn build_router(state: AppState) -> Router {
+    let shared_state = Arc::new(state);
+    let admin_only = middleware::from_fn_with_state(shared_state.clone(), require_admin);
+    let api_router = Router::new()
+        .route("/", get(hello_world))
+        .route("/-1/seek", get(with_status_and_array_headers))
+        .route("/2/dest", get(ipv4_encryption))
+        .route("/2/key", get(extract_ipv4_key))
+        .route("/2/v6/dest", get(ipv6_encryption))
+        .route("/2/v6/key", get(extract_ipv6_key))
+        .route("/5/manifest", post(parse_manifest))
+        .route("/9/milk", post(withdraw_milk))
+        .route("/9/refill", post(refill_milk).layer(admin_only.clone()))
+        .route("/12/board", get(current_board))
+        .route("/12/reset", post(reset_board).layer(admin_only.clone()))
+        .route("/12/place/:team/:column", post(place_item))
+        .route("/12/random-board", post(random))
+        .route("/12/ai-move/:team", post(ai_move))
+        .route("/12/history", get(history))
+        .route("/12/undo", post(undo_move))
+        .route("/12/replay/:moves", get(replay))
+        .route("/12/export", get(export_moves))
+        .route("/12/import", post(import_moves))
+        .route("/12/games", post(create_game))
+        .route("/12/games/:id/board", get(current_board))
+        .route(
+            "/12/games/:id/reset",
+            post(reset_board).layer(admin_only.clone()),
+        )
+        .route("/12/games/:id/place/:team/:column", post(place_item))
+        .route("/12/games/:id/random-board", post(random))
+        .route("/12/games/:id/ai-move/:team", post(ai_move))
+        .route("/12/games/:id/history", get(history))
+        .route("/12/games/:id/undo", post(undo_move))
+        .route("/12/games/:id/replay/:moves", get(replay))
+        .route("/12/games/:id/export", get(export_moves))
+        .route("/12/games/:id/import", post(import_moves))
+        .route("/16/wrap", post(wrap_present))
+        .route("/16/unwrap", get(unwrap_present))
+        .route(
+            "/19/draft",
+            post(draft).layer(DefaultBodyLimit::max(PAYLOAD_LIMIT)),
+        )
+        .route("/19/reset", post(reset).layer(admin_only))
+        .route("/19/cite/:id", get(cite_by_id))
+        .route("/19/remove/:id", delete(remove_by_id))
+        .route(
+            "/19/undo/:id",
+            put(undo_by_id).layer(DefaultBodyLimit::max(PAYLOAD_LIMIT)),
+        )
+        .route("/19/list", get(list))
+        .route("/19/search", get(search))
+        .route("/19/export", get(export))
+        .route("/19/import", post(import))
+        .route("/19/authors", get(authors))
+        .route("/19/authors/:id/quotes", get(quotes_by_author))
+        .route("/19/random", get(random_quote))
+        .route("/19/daily", get(daily_quote))
+        .route("/19/audit", get(audit_log))
+        .route("/19/tags", get(tags))
+        .route("/19/tags/:id/:tag", put(tag_quote).delete(untag_quote))
+        .route("/19/votes/:id/:voter", put(vote_quote).delete(unvote_quote))
+        .route("/19/top", get(top))
+        .route("/19/events", get(events))
+        .route("/19/history/:id", get(history_by_id))
+        .route("/19/history/:id/:version", get(version_by_id))
+        .route("/19/revert/:id/:version", post(revert_by_id))
+        .route("/19/restore/:id", post(restore_by_id))
+        .route("/19/purge/:id", delete(purge_by_id))
+        .route("/23/star", get(star))
+        .route("/23/present/:color", get(present))
+        .route("/23/ornament/:state/:n", get(ornament))
+        .with_state(shared_state);
+
+    let assets_service = ServiceBuilder::new().service(ServeDir::new("assets"));
+
+    Router::new()
+        .nest_service("/", api_router)
+        .nest_service("/assets", assets_service)
+}
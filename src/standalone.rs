@@ -0,0 +1,170 @@
+//! Entry point for running the service without the Shuttle runtime.
+//!
+//! Configuration is read from the environment and can be overridden on the
+//! command line:
+//!
+//! | flag                     | env                    | default        |
+//! |--------------------------|------------------------|----------------|
+//! | `--bind`                 | `BIND_ADDR`            | `0.0.0.0:8000` |
+//! | `--database-url`         | `DATABASE_URL`         | `memory`       |
+//! | `--quote-retention-secs` | `QUOTE_RETENTION_SECS` | 30 days        |
+//! | `--require-if-match`     | `REQUIRE_IF_MATCH`     | `false`        |
+//! | `--quote-seed`           | `QUOTE_SEED`           | `2024`         |
+//! | `--admin-token`          | `ADMIN_TOKEN`          | unset          |
+//! | `--admin-jwt-secret`     | `ADMIN_JWT_SECRET`     | unset          |
+//! | `--game-ttl-secs`        | `GAME_TTL_SECS`        | 1 hour         |
+//!
+//! The reset and refill routes are open to everyone unless an admin token or
+//! JWT secret is set.
+//!
+//! The database URL also selects the quote store backend, see
+//! [`shuttlings_cch24::store::connect`].
+
+use std::{env, error::Error, net::SocketAddr, time::Duration};
+
+use shuttlings_cch24::{
+    auth::AdminAuth,
+    build_router,
+    day::day12::{Games, DEFAULT_GAME_TTL},
+    store::{self, spawn_purge_job, DEFAULT_RETENTION},
+    AppState, DEFAULT_QUOTE_SEED,
+};
+use tokio::{net::TcpListener, signal};
+
+const DEFAULT_BIND_ADDR: &str = "0.0.0.0:8000";
+const DEFAULT_DATABASE_URL: &str = "memory";
+
+#[derive(Debug)]
+struct Config {
+    bind_addr: SocketAddr,
+    database_url: String,
+    /// How long soft-deleted quotes are kept before being purged.
+    quote_retention: Duration,
+    /// Whether quote updates and deletes must send `If-Match`.
+    require_if_match: bool,
+    /// Seed for random quotes and the quote of the day.
+    quote_seed: u64,
+    /// Credentials for the reset and refill routes.
+    admin: AdminAuth,
+    /// How long day 12 games may sit idle before they are dropped.
+    game_ttl: Duration,
+}
+
+impl Config {
+    fn load() -> Result<Config, Box<dyn Error + Send + Sync>> {
+        let mut bind_addr = env::var("BIND_ADDR").ok();
+        let mut database_url = env::var("DATABASE_URL").ok();
+        let mut quote_retention = env::var("QUOTE_RETENTION_SECS").ok();
+        let mut require_if_match = env::var("REQUIRE_IF_MATCH").ok();
+        let mut quote_seed = env::var("QUOTE_SEED").ok();
+        let mut admin_token = env::var("ADMIN_TOKEN").ok();
+        let mut admin_jwt_secret = env::var("ADMIN_JWT_SECRET").ok();
+        let mut game_ttl = env::var("GAME_TTL_SECS").ok();
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let target = match flag.as_str() {
+                "--bind" => &mut bind_addr,
+                "--database-url" => &mut database_url,
+                "--quote-retention-secs" => &mut quote_retention,
+                "--require-if-match" => &mut require_if_match,
+                "--quote-seed" => &mut quote_seed,
+                "--admin-token" => &mut admin_token,
+                "--admin-jwt-secret" => &mut admin_jwt_secret,
+                "--game-ttl-secs" => &mut game_ttl,
+                _ => return Err(format!("unknown argument: {flag}").into()),
+            };
+            let value = inline
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("missing value for {flag}"))?;
+            *target = Some(value);
+        }
+
+        Ok(Config {
+            bind_addr: bind_addr.as_deref().unwrap_or(DEFAULT_BIND_ADDR).parse()?,
+            database_url: database_url.unwrap_or_else(|| DEFAULT_DATABASE_URL.to_string()),
+            quote_retention: match quote_retention {
+                Some(secs) => Duration::from_secs(secs.parse()?),
+                None => DEFAULT_RETENTION,
+            },
+            require_if_match: match require_if_match {
+                Some(value) => value.parse()?,
+                None => false,
+            },
+            quote_seed: match quote_seed {
+                Some(seed) => seed.parse()?,
+                None => DEFAULT_QUOTE_SEED,
+            },
+            admin: AdminAuth {
+                token: admin_token,
+                jwt_secret: admin_jwt_secret,
+            },
+            game_ttl: match game_ttl {
+                Some(secs) => Duration::from_secs(secs.parse()?),
+                None => DEFAULT_GAME_TTL,
+            },
+        })
+    }
+}
+
+pub async fn run() -> Result<(), Box<dyn Error + Send + Sync>> {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .init();
+
+    let config = Config::load()?;
+
+    let quotes = store::connect(&config.database_url).await?;
+    spawn_purge_job(quotes.clone(), config.quote_retention);
+
+    let listener = TcpListener::bind(config.bind_addr).await?;
+    tracing::info!("listening on {}", listener.local_addr()?);
+
+    if config.admin.is_open() {
+        tracing::warn!("no admin credentials configured, reset and refill routes are open");
+    }
+
+    let state = AppState {
+        require_if_match: config.require_if_match,
+        admin: config.admin,
+        games: Games::new(config.game_ttl),
+        ..AppState::new(quotes)
+    }
+    .with_quote_seed(config.quote_seed);
+    axum::serve(listener, build_router(state))
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    Ok(())
+}
+
+/// Resolves on Ctrl+C or SIGTERM so in-flight requests can drain.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("shutdown signal received");
+}
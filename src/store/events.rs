@@ -0,0 +1,120 @@
+//! Change feed behind the day 19 event stream.
+
+use std::{collections::VecDeque, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use tokio::sync::broadcast;
+
+use super::Quote;
+
+/// How many events subscribers may fall behind by, and how many the
+/// in-process bus keeps for resumes.
+pub const EVENT_BACKLOG: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuoteEventKind {
+    /// A new quote, or one restored from the trash.
+    Created,
+    Updated,
+    /// Moved to the trash.
+    Deleted,
+}
+
+impl QuoteEventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuoteEventKind::Created => "created",
+            QuoteEventKind::Updated => "updated",
+            QuoteEventKind::Deleted => "deleted",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "created" => Some(QuoteEventKind::Created),
+            "updated" => Some(QuoteEventKind::Updated),
+            "deleted" => Some(QuoteEventKind::Deleted),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuoteEvent {
+    /// Increases with every event, also across resets.
+    pub id: i64,
+    pub kind: QuoteEventKind,
+    /// The quote right after the change.
+    pub quote: Quote,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Fans quote events out to subscribers in this process.
+#[derive(Clone, Debug)]
+pub struct EventBus {
+    sender: broadcast::Sender<QuoteEvent>,
+    recent: Arc<Mutex<Recent>>,
+}
+
+#[derive(Debug, Default)]
+struct Recent {
+    last_id: i64,
+    events: VecDeque<QuoteEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BACKLOG);
+        EventBus {
+            sender,
+            recent: Arc::default(),
+        }
+    }
+}
+
+impl EventBus {
+    /// Numbers, keeps and sends an event for a change made in this process.
+    pub fn publish(&self, kind: QuoteEventKind, quote: &Quote) {
+        let mut recent = self.recent.lock();
+        recent.last_id += 1;
+        let event = QuoteEvent {
+            id: recent.last_id,
+            kind,
+            quote: quote.clone(),
+            created_at: Utc::now(),
+        };
+        if recent.events.len() == EVENT_BACKLOG {
+            recent.events.pop_front();
+        }
+        recent.events.push_back(event.clone());
+        // Sending under the lock keeps subscribers seeing events in id order.
+        let _ = self.sender.send(event);
+    }
+
+    /// Sends an event numbered and kept elsewhere, like in Postgres.
+    pub fn forward(&self, event: QuoteEvent) {
+        // Nobody listening is fine.
+        let _ = self.sender.send(event);
+    }
+
+    /// Kept events after `after`, oldest first.
+    pub fn since(&self, after: i64) -> Vec<QuoteEvent> {
+        let recent = self.recent.lock();
+        recent
+            .events
+            .iter()
+            .filter(|event| event.id > after)
+            .cloned()
+            .collect()
+    }
+
+    /// Forgets kept events; numbering carries on where it was.
+    pub fn clear(&self) {
+        self.recent.lock().events.clear();
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<QuoteEvent> {
+        self.sender.subscribe()
+    }
+}
@@ -0,0 +1,538 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use super::{
+    naive_search, normalize_author, rank_top, AuditEntry, AuditQuery, AuditRecord, AuthorStats,
+    EventBus, Quote, QuoteEvent, QuoteEventKind, QuoteStore, QuoteVersion, RatedQuote, Rating,
+    SearchHit, SearchQuery, StoreResult, TagCount, TagFilter, Vote, PURGE_ACTOR,
+};
+
+/// Process-local store for development and tests; nothing is persisted.
+#[derive(Debug, Default)]
+pub struct MemoryQuoteStore {
+    inner: RwLock<Inner>,
+    events: EventBus,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    quotes: HashMap<Uuid, Quote>,
+    versions: HashMap<Uuid, Vec<QuoteVersion>>,
+    /// Author id and display name by normalized name.
+    authors: HashMap<String, (Uuid, String)>,
+    tags: HashMap<Uuid, BTreeSet<String>>,
+    /// Votes on a quote by voter.
+    votes: HashMap<Uuid, HashMap<String, Vote>>,
+    /// Oldest first; kept across resets.
+    audit: Vec<AuditEntry>,
+}
+
+impl Inner {
+    fn live(&self, id: Uuid) -> Option<&Quote> {
+        self.quotes
+            .get(&id)
+            .filter(|quote| quote.deleted_at.is_none())
+    }
+
+    fn live_mut(&mut self, id: Uuid) -> Option<&mut Quote> {
+        self.quotes
+            .get_mut(&id)
+            .filter(|quote| quote.deleted_at.is_none())
+    }
+
+    fn deleted_mut(&mut self, id: Uuid) -> Option<&mut Quote> {
+        self.quotes
+            .get_mut(&id)
+            .filter(|quote| quote.deleted_at.is_some())
+    }
+
+    /// Live quotes passing `filter`, in no particular order.
+    fn tagged<'a>(&'a self, filter: &'a TagFilter) -> impl Iterator<Item = &'a Quote> + 'a {
+        self.quotes
+            .values()
+            .filter(|quote| quote.deleted_at.is_none())
+            .filter(|quote| filter.matches(self.tags.get(&quote.id).into_iter().flatten()))
+    }
+
+    fn remove_quote(&mut self, id: Uuid) -> Option<Quote> {
+        self.versions.remove(&id);
+        self.tags.remove(&id);
+        self.votes.remove(&id);
+        self.quotes.remove(&id)
+    }
+
+    /// Rating of a quote, `None` without votes.
+    fn rating(&self, id: Uuid) -> Option<Rating> {
+        let votes = self.votes.get(&id).filter(|votes| !votes.is_empty())?;
+        let upvotes = votes.values().filter(|&&vote| vote == Vote::Up).count() as i64;
+        Some(Rating {
+            upvotes,
+            downvotes: votes.len() as i64 - upvotes,
+        })
+    }
+
+    /// Creates the author `name` normalizes to unless it already exists.
+    fn file_author(&mut self, name: &str) {
+        self.authors
+            .entry(normalize_author(name))
+            .or_insert_with(|| (Uuid::new_v4(), name.trim().to_string()));
+    }
+
+    /// Replaces author and quote of a live quote, returning it from before
+    /// and after.
+    fn update(
+        &mut self,
+        id: Uuid,
+        author: &str,
+        quote: &str,
+        expected_version: Option<i32>,
+    ) -> Option<(Quote, Quote)> {
+        let stored = self
+            .live_mut(id)
+            .filter(|stored| expected_version.is_none_or(|v| v == stored.version))?;
+        let before = stored.clone();
+        stored.author = author.to_string();
+        stored.quote = quote.to_string();
+        stored.version += 1;
+        let updated = stored.clone();
+        self.file_author(author);
+        self.record_version(&updated);
+        Some((before, updated))
+    }
+
+    fn append_audit(&mut self, record: AuditRecord) -> AuditEntry {
+        let entry = AuditEntry {
+            id: self.audit.len() as i64 + 1,
+            record,
+            created_at: Utc::now(),
+        };
+        self.audit.push(entry.clone());
+        entry
+    }
+
+    fn record_version(&mut self, quote: &Quote) {
+        self.versions
+            .entry(quote.id)
+            .or_default()
+            .push(QuoteVersion {
+                quote_id: quote.id,
+                version: quote.version,
+                author: quote.author.clone(),
+                quote: quote.quote.clone(),
+                created_at: Utc::now(),
+            });
+    }
+}
+
+#[async_trait]
+impl QuoteStore for MemoryQuoteStore {
+    async fn insert(
+        &self,
+        id: Uuid,
+        author: &str,
+        quote: &str,
+        actor: Option<&str>,
+    ) -> StoreResult<Quote> {
+        let mut inner = self.inner.write();
+        if inner.quotes.contains_key(&id) {
+            return Err(sqlx::Error::Protocol(format!("duplicate quote id {id}")));
+        }
+        inner.file_author(author);
+        let quote = Quote {
+            id,
+            author: author.to_string(),
+            quote: quote.to_string(),
+            created_at: Utc::now(),
+            version: 1,
+            deleted_at: None,
+        };
+        inner.quotes.insert(id, quote.clone());
+        inner.record_version(&quote);
+        inner.append_audit(AuditRecord::quote("create", actor, None, Some(&quote)));
+        self.events.publish(QuoteEventKind::Created, &quote);
+        Ok(quote)
+    }
+
+    async fn get(&self, id: Uuid) -> StoreResult<Option<Quote>> {
+        Ok(self.inner.read().live(id).cloned())
+    }
+
+    async fn update(
+        &self,
+        id: Uuid,
+        author: &str,
+        quote: &str,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut inner = self.inner.write();
+        let Some((before, updated)) = inner.update(id, author, quote, expected_version) else {
+            return Ok(None);
+        };
+        let record = AuditRecord::quote("update", actor, Some(&before), Some(&updated));
+        inner.append_audit(record);
+        self.events.publish(QuoteEventKind::Updated, &updated);
+        Ok(Some(updated))
+    }
+
+    async fn remove(
+        &self,
+        id: Uuid,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut inner = self.inner.write();
+        let Some(stored) = inner
+            .live_mut(id)
+            .filter(|stored| expected_version.is_none_or(|v| v == stored.version))
+        else {
+            return Ok(None);
+        };
+        stored.deleted_at = Some(Utc::now());
+        let removed = stored.clone();
+        inner.append_audit(AuditRecord::quote("delete", actor, Some(&removed), None));
+        self.events.publish(QuoteEventKind::Deleted, &removed);
+        Ok(Some(removed))
+    }
+
+    async fn get_deleted(&self, id: Uuid) -> StoreResult<Option<Quote>> {
+        Ok(self
+            .inner
+            .read()
+            .quotes
+            .get(&id)
+            .filter(|quote| quote.deleted_at.is_some())
+            .cloned())
+    }
+
+    async fn restore(
+        &self,
+        id: Uuid,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut inner = self.inner.write();
+        let Some(stored) = inner
+            .deleted_mut(id)
+            .filter(|stored| expected_version.is_none_or(|v| v == stored.version))
+        else {
+            return Ok(None);
+        };
+        stored.deleted_at = None;
+        let restored = stored.clone();
+        inner.append_audit(AuditRecord::quote("restore", actor, None, Some(&restored)));
+        self.events.publish(QuoteEventKind::Created, &restored);
+        Ok(Some(restored))
+    }
+
+    async fn purge(
+        &self,
+        id: Uuid,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut inner = self.inner.write();
+        if inner
+            .deleted_mut(id)
+            .filter(|stored| expected_version.is_none_or(|v| v == stored.version))
+            .is_none()
+        {
+            return Ok(None);
+        }
+        let purged = inner.remove_quote(id);
+        if let Some(purged) = &purged {
+            inner.append_audit(AuditRecord::quote("purge", actor, Some(purged), None));
+        }
+        Ok(purged)
+    }
+
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> StoreResult<u64> {
+        let mut inner = self.inner.write();
+        let expired: Vec<_> = inner
+            .quotes
+            .values()
+            .filter(|quote| quote.deleted_at.is_some_and(|at| at < cutoff))
+            .map(|quote| quote.id)
+            .collect();
+        for id in &expired {
+            if let Some(purged) = inner.remove_quote(*id) {
+                let record = AuditRecord::quote("purge", Some(PURGE_ACTOR), Some(&purged), None);
+                inner.append_audit(record);
+            }
+        }
+        Ok(expired.len() as u64)
+    }
+
+    async fn list(&self, offset: i64, limit: i64, tags: &TagFilter) -> StoreResult<Vec<Quote>> {
+        let mut quotes: Vec<_> = self.inner.read().tagged(tags).cloned().collect();
+        quotes.sort_by_key(|quote| (quote.created_at, quote.id));
+        Ok(quotes
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    async fn list_after(
+        &self,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> StoreResult<Vec<Quote>> {
+        let mut quotes: Vec<_> = self
+            .inner
+            .read()
+            .tagged(&TagFilter::default())
+            .filter(|quote| after.is_none_or(|after| (quote.created_at, quote.id) > after))
+            .cloned()
+            .collect();
+        quotes.sort_by_key(|quote| (quote.created_at, quote.id));
+        quotes.truncate(limit.max(0) as usize);
+        Ok(quotes)
+    }
+
+    async fn count(&self) -> StoreResult<i64> {
+        Ok(self.inner.read().tagged(&TagFilter::default()).count() as i64)
+    }
+
+    async fn search(&self, query: &SearchQuery) -> StoreResult<Vec<SearchHit>> {
+        Ok(naive_search(
+            self.inner.read().tagged(&query.tags).cloned(),
+            query,
+        ))
+    }
+
+    async fn authors(&self) -> StoreResult<Vec<AuthorStats>> {
+        let inner = self.inner.read();
+        let mut stats: HashMap<&str, AuthorStats> = HashMap::new();
+        for quote in inner.quotes.values().filter(|q| q.deleted_at.is_none()) {
+            let normalized = normalize_author(&quote.author);
+            let Some((key, (id, name))) = inner.authors.get_key_value(&normalized) else {
+                continue;
+            };
+            stats
+                .entry(key)
+                .and_modify(|author| {
+                    author.quote_count += 1;
+                    author.first_quote_at = author.first_quote_at.min(quote.created_at);
+                    author.last_quote_at = author.last_quote_at.max(quote.created_at);
+                })
+                .or_insert_with(|| AuthorStats {
+                    id: *id,
+                    name: name.clone(),
+                    quote_count: 1,
+                    first_quote_at: quote.created_at,
+                    last_quote_at: quote.created_at,
+                });
+        }
+        let mut stats: Vec<_> = stats.into_iter().collect();
+        stats.sort_by_key(|(normalized, _)| *normalized);
+        Ok(stats.into_iter().map(|(_, author)| author).collect())
+    }
+
+    async fn quotes_by_author(&self, author_id: Uuid) -> StoreResult<Option<Vec<Quote>>> {
+        let inner = self.inner.read();
+        let Some(normalized) = inner
+            .authors
+            .iter()
+            .find(|(_, (id, _))| *id == author_id)
+            .map(|(normalized, _)| normalized)
+        else {
+            return Ok(None);
+        };
+        let mut quotes: Vec<_> = inner
+            .quotes
+            .values()
+            .filter(|quote| quote.deleted_at.is_none())
+            .filter(|quote| normalize_author(&quote.author) == *normalized)
+            .cloned()
+            .collect();
+        quotes.sort_by_key(|quote| (quote.created_at, quote.id));
+        Ok(Some(quotes))
+    }
+
+    async fn tag(&self, id: Uuid, tag: &str) -> StoreResult<Option<Vec<String>>> {
+        let mut inner = self.inner.write();
+        if inner.live(id).is_none() {
+            return Ok(None);
+        }
+        let tags = inner.tags.entry(id).or_default();
+        tags.insert(tag.to_string());
+        Ok(Some(tags.iter().cloned().collect()))
+    }
+
+    async fn untag(&self, id: Uuid, tag: &str) -> StoreResult<Option<Vec<String>>> {
+        let mut inner = self.inner.write();
+        if inner.live(id).is_none() {
+            return Ok(None);
+        }
+        let tags = inner.tags.entry(id).or_default();
+        tags.remove(tag);
+        Ok(Some(tags.iter().cloned().collect()))
+    }
+
+    async fn tags(&self) -> StoreResult<Vec<TagCount>> {
+        let inner = self.inner.read();
+        let mut counts: BTreeMap<&str, i64> = BTreeMap::new();
+        for quote in inner.tagged(&TagFilter::default()) {
+            for tag in inner.tags.get(&quote.id).into_iter().flatten() {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+        Ok(counts
+            .into_iter()
+            .map(|(name, quote_count)| TagCount {
+                name: name.to_string(),
+                quote_count,
+            })
+            .collect())
+    }
+
+    async fn vote(&self, id: Uuid, voter: &str, vote: Option<Vote>) -> StoreResult<Option<Rating>> {
+        let mut inner = self.inner.write();
+        if inner.live(id).is_none() {
+            return Ok(None);
+        }
+        let votes = inner.votes.entry(id).or_default();
+        match vote {
+            Some(vote) => votes.insert(voter.to_string(), vote),
+            None => votes.remove(voter),
+        };
+        Ok(Some(inner.rating(id).unwrap_or_default()))
+    }
+
+    async fn ratings(&self, ids: &[Uuid]) -> StoreResult<HashMap<Uuid, Rating>> {
+        let inner = self.inner.read();
+        Ok(ids
+            .iter()
+            .filter_map(|&id| Some((id, inner.rating(id)?)))
+            .collect())
+    }
+
+    async fn top(&self, offset: i64, limit: i64) -> StoreResult<Vec<RatedQuote>> {
+        let inner = self.inner.read();
+        let rated = inner
+            .tagged(&TagFilter::default())
+            .filter_map(|quote| {
+                Some(RatedQuote {
+                    rating: inner.rating(quote.id)?,
+                    quote: quote.clone(),
+                })
+            })
+            .collect();
+        Ok(rank_top(rated, offset, limit))
+    }
+
+    async fn events_since(&self, after: i64) -> StoreResult<Vec<QuoteEvent>> {
+        Ok(self.events.since(after))
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<QuoteEvent> {
+        self.events.subscribe()
+    }
+
+    async fn append_audit(&self, record: &AuditRecord) -> StoreResult<AuditEntry> {
+        Ok(self.inner.write().append_audit(record.clone()))
+    }
+
+    async fn audit(&self, query: &AuditQuery) -> StoreResult<Vec<AuditEntry>> {
+        Ok(self
+            .inner
+            .read()
+            .audit
+            .iter()
+            .rev()
+            .filter(|entry| {
+                query
+                    .quote_id
+                    .is_none_or(|id| entry.record.quote_id == Some(id))
+            })
+            .filter(|entry| query.since.is_none_or(|since| entry.created_at >= since))
+            .filter(|entry| query.until.is_none_or(|until| entry.created_at < until))
+            .skip(query.offset.max(0) as usize)
+            .take(query.limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn import(&self, quotes: &[Quote], actor: Option<&str>) -> StoreResult<Vec<Uuid>> {
+        let mut inner = self.inner.write();
+        let taken: Vec<_> = quotes
+            .iter()
+            .map(|quote| quote.id)
+            .filter(|id| inner.quotes.contains_key(id))
+            .collect();
+        if !taken.is_empty() {
+            return Ok(taken);
+        }
+        for quote in quotes {
+            inner.file_author(&quote.author);
+            inner.quotes.insert(quote.id, quote.clone());
+            inner.record_version(quote);
+            inner.append_audit(AuditRecord::quote("import", actor, None, Some(quote)));
+            self.events.publish(QuoteEventKind::Created, quote);
+        }
+        Ok(taken)
+    }
+
+    async fn reset(&self, actor: Option<&str>) -> StoreResult<()> {
+        let mut inner = self.inner.write();
+        *inner = Inner {
+            audit: std::mem::take(&mut inner.audit),
+            ..Inner::default()
+        };
+        inner.append_audit(AuditRecord::book("reset", actor));
+        self.events.clear();
+        Ok(())
+    }
+
+    async fn history(&self, id: Uuid) -> StoreResult<Option<Vec<QuoteVersion>>> {
+        let inner = self.inner.read();
+        if inner.live(id).is_none() {
+            return Ok(None);
+        }
+        Ok(inner.versions.get(&id).cloned())
+    }
+
+    async fn version(&self, id: Uuid, version: i32) -> StoreResult<Option<QuoteVersion>> {
+        let inner = self.inner.read();
+        if inner.live(id).is_none() {
+            return Ok(None);
+        }
+        Ok(inner
+            .versions
+            .get(&id)
+            .and_then(|versions| versions.iter().find(|v| v.version == version))
+            .cloned())
+    }
+
+    async fn revert(
+        &self,
+        id: Uuid,
+        version: i32,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut inner = self.inner.write();
+        let Some(old) = inner
+            .live(id)
+            .and(inner.versions.get(&id))
+            .and_then(|versions| versions.iter().find(|v| v.version == version))
+            .cloned()
+        else {
+            return Ok(None);
+        };
+        let Some((before, updated)) = inner.update(id, &old.author, &old.quote, expected_version)
+        else {
+            return Ok(None);
+        };
+        let record = AuditRecord::quote("revert", actor, Some(&before), Some(&updated));
+        inner.append_audit(record);
+        self.events.publish(QuoteEventKind::Updated, &updated);
+        Ok(Some(updated))
+    }
+}
@@ -0,0 +1,560 @@
+//! Storage backends for the day 19 quote book.
+//!
+//! The backend is picked at startup from the database URL, see [`connect`].
+
+use std::{collections::HashMap, error::Error, fmt::Debug, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tera::escape_html;
+use tokio::{sync::broadcast, task::JoinHandle};
+use uuid::Uuid;
+
+pub mod events;
+pub mod memory;
+pub mod postgres;
+pub mod sqlite;
+
+pub use events::{EventBus, QuoteEvent, QuoteEventKind, EVENT_BACKLOG};
+pub use memory::MemoryQuoteStore;
+pub use postgres::PgQuoteStore;
+pub use sqlite::SqliteQuoteStore;
+
+pub type StoreResult<T> = Result<T, sqlx::Error>;
+
+/// How long soft-deleted quotes are kept before the purge job removes them.
+pub const DEFAULT_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Shortest time between purges, for retentions down to zero.
+const MIN_PURGE_INTERVAL: Duration = Duration::from_secs(1);
+/// Who the purge job's deletions are audited as.
+pub const PURGE_ACTOR: &str = "system";
+
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct Quote {
+    pub id: Uuid,
+    pub author: String,
+    pub quote: String,
+    pub created_at: DateTime<Utc>,
+    pub version: i32,
+    /// Set while the quote sits in the trash, see [`QuoteStore::remove`].
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Snapshot of a quote as it was written at `version`.
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct QuoteVersion {
+    pub quote_id: Uuid,
+    pub version: i32,
+    pub author: String,
+    pub quote: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An author with statistics over their live quotes.
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct AuthorStats {
+    pub id: Uuid,
+    /// Spelling used by the author's first quote.
+    pub name: String,
+    pub quote_count: i64,
+    pub first_quote_at: DateTime<Utc>,
+    pub last_quote_at: DateTime<Utc>,
+}
+
+/// Key under which differently written author names are merged into one
+/// author: whitespace trimmed and collapsed, then lowercased.
+pub fn normalize_author(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Key tags are stored under: trimmed and lowercased.
+pub fn normalize_tag(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct TagCount {
+    pub name: String,
+    /// Live quotes carrying the tag.
+    pub quote_count: i64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    /// Quotes carrying every tag.
+    #[default]
+    All,
+    /// Quotes carrying at least one of the tags.
+    Any,
+}
+
+/// Restricts listings to tagged quotes; an empty filter lets everything pass.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TagFilter {
+    /// Normalized and free of duplicates.
+    pub tags: Vec<String>,
+    pub mode: TagMatch,
+}
+
+impl TagFilter {
+    /// How many of the filter's tags a quote has to carry to pass.
+    pub fn min_matches(&self) -> i64 {
+        match (self.tags.is_empty(), self.mode) {
+            (true, _) => 0,
+            (false, TagMatch::All) => self.tags.len() as i64,
+            (false, TagMatch::Any) => 1,
+        }
+    }
+
+    pub fn matches<'a>(&self, tags: impl IntoIterator<Item = &'a String>) -> bool {
+        let matching = tags
+            .into_iter()
+            .filter(|tag| self.tags.contains(tag))
+            .count();
+        matching as i64 >= self.min_matches()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Vote {
+    Up,
+    Down,
+}
+
+impl Vote {
+    /// How the vote is stored: 1 for up, -1 for down.
+    pub fn value(self) -> i16 {
+        match self {
+            Vote::Up => 1,
+            Vote::Down => -1,
+        }
+    }
+}
+
+/// Votes cast on a quote.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rating {
+    pub upvotes: i64,
+    pub downvotes: i64,
+}
+
+impl Rating {
+    /// Lower bound of the 95% Wilson score interval of the upvote share, so a
+    /// handful of votes ranks below many votes with the same share.
+    pub fn score(&self) -> f64 {
+        let n = (self.upvotes + self.downvotes) as f64;
+        if n == 0.0 {
+            return 0.0;
+        }
+        let p = self.upvotes as f64 / n;
+        let z2 = WILSON_Z * WILSON_Z;
+        (p + z2 / (2.0 * n) - WILSON_Z * ((p * (1.0 - p) + z2 / (4.0 * n)) / n).sqrt())
+            / (1.0 + z2 / n)
+    }
+}
+
+/// Quantile of the standard normal distribution for 95% confidence.
+const WILSON_Z: f64 = 1.96;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RatedQuote {
+    pub quote: Quote,
+    pub rating: Rating,
+}
+
+/// One mutation to record in the audit log.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditRecord {
+    /// What happened, e.g. `create` or `delete`.
+    pub action: String,
+    /// Unset for actions on the whole book, like a reset.
+    pub quote_id: Option<Uuid>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    /// Who asked for the change, if known.
+    pub actor: Option<String>,
+}
+
+impl AuditRecord {
+    /// Record of `action` on one quote, which went from `before` to `after`.
+    pub fn quote(
+        action: &str,
+        actor: Option<&str>,
+        before: Option<&Quote>,
+        after: Option<&Quote>,
+    ) -> Self {
+        AuditRecord {
+            action: action.to_string(),
+            quote_id: before.or(after).map(|quote| quote.id),
+            before: before.map(snapshot),
+            after: after.map(snapshot),
+            actor: actor.map(str::to_string),
+        }
+    }
+
+    /// Record of `action` on the whole book.
+    pub fn book(action: &str, actor: Option<&str>) -> Self {
+        AuditRecord {
+            action: action.to_string(),
+            quote_id: None,
+            before: None,
+            after: None,
+            actor: actor.map(str::to_string),
+        }
+    }
+}
+
+/// A quote as the API shows it, leaving out deletion state.
+fn snapshot(quote: &Quote) -> serde_json::Value {
+    serde_json::json!({
+        "id": quote.id,
+        "author": quote.author,
+        "quote": quote.quote,
+        "created_at": quote.created_at,
+        "version": quote.version,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub record: AuditRecord,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Audit log filter; unset fields match everything.
+#[derive(Clone, Debug, Default)]
+pub struct AuditQuery {
+    pub quote_id: Option<Uuid>,
+    /// Inclusive lower bound on the entry time.
+    pub since: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on the entry time.
+    pub until: Option<DateTime<Utc>>,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+/// Full-text search request; at least one of `text` and `author` is set.
+#[derive(Clone, Debug, Default)]
+pub struct SearchQuery {
+    /// Free text matched against author and quote.
+    pub text: Option<String>,
+    /// Exact author, compared case-insensitively.
+    pub author: Option<String>,
+    pub tags: TagFilter,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchHit {
+    pub quote: Quote,
+    /// Higher is better; only comparable within one search.
+    pub rank: f32,
+    /// The quote escaped for HTML, with matched terms wrapped in `<mark>` tags.
+    pub snippet: String,
+}
+
+/// Operations the quote book handlers need from a backend.
+///
+/// Every write files the quote under the author its name normalizes to, see
+/// [`normalize_author`], creating the author when needed.
+///
+/// Lookups return `Ok(None)` when the quote doesn't exist, errors are
+/// reserved for backend failures. Soft-deleted quotes are invisible to
+/// everything but [`restore`](QuoteStore::restore) and the purge methods.
+///
+/// Mutations made on behalf of a client take its `actor` and write their
+/// audit record atomically with the change itself.
+#[async_trait]
+pub trait QuoteStore: Debug + Send + Sync {
+    async fn insert(
+        &self,
+        id: Uuid,
+        author: &str,
+        quote: &str,
+        actor: Option<&str>,
+    ) -> StoreResult<Quote>;
+
+    async fn get(&self, id: Uuid) -> StoreResult<Option<Quote>>;
+
+    /// Replaces author and quote, bumping the version. Every version is kept
+    /// in the quote's history.
+    ///
+    /// With `expected_version` set, nothing happens unless the quote is still
+    /// at that version.
+    async fn update(
+        &self,
+        id: Uuid,
+        author: &str,
+        quote: &str,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>>;
+
+    /// Soft-deletes a quote; it can be brought back with `restore` until it
+    /// is purged. `expected_version` works as for [`update`](QuoteStore::update).
+    async fn remove(
+        &self,
+        id: Uuid,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>>;
+
+    /// A soft-deleted quote, for checking preconditions on restore and purge.
+    async fn get_deleted(&self, id: Uuid) -> StoreResult<Option<Quote>>;
+
+    /// Brings back a soft-deleted quote. `expected_version` works as for
+    /// [`update`](QuoteStore::update).
+    async fn restore(
+        &self,
+        id: Uuid,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>>;
+
+    /// Permanently deletes a soft-deleted quote. `expected_version` works as
+    /// for [`update`](QuoteStore::update).
+    async fn purge(
+        &self,
+        id: Uuid,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>>;
+
+    /// Permanently deletes quotes soft-deleted before `cutoff`, auditing each
+    /// as purged by [`PURGE_ACTOR`], and returns how many were removed. Events beyond the latest [`EVENT_BACKLOG`] may be
+    /// dropped too, whatever their age.
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> StoreResult<u64>;
+
+    /// Quotes passing `tags`, ordered by creation time, oldest first.
+    async fn list(&self, offset: i64, limit: i64, tags: &TagFilter) -> StoreResult<Vec<Quote>>;
+
+    /// Live quotes after `after` in `(created_at, id)` order, so a walk over
+    /// the whole book in batches neither skips nor repeats quotes when others
+    /// are added or removed in between.
+    async fn list_after(
+        &self,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> StoreResult<Vec<Quote>>;
+
+    /// Number of live quotes.
+    async fn count(&self) -> StoreResult<i64>;
+
+    /// Matching quotes, best match first.
+    async fn search(&self, query: &SearchQuery) -> StoreResult<Vec<SearchHit>>;
+
+    /// Authors with at least one live quote, ordered by normalized name.
+    async fn authors(&self) -> StoreResult<Vec<AuthorStats>>;
+
+    /// Live quotes by an author, oldest first, or `None` for unknown authors.
+    async fn quotes_by_author(&self, author_id: Uuid) -> StoreResult<Option<Vec<Quote>>>;
+
+    /// Adds a tag to a live quote and returns all its tags, sorted.
+    async fn tag(&self, id: Uuid, tag: &str) -> StoreResult<Option<Vec<String>>>;
+
+    /// Removes a tag from a live quote and returns the remaining ones.
+    async fn untag(&self, id: Uuid, tag: &str) -> StoreResult<Option<Vec<String>>>;
+
+    /// Tags on at least one live quote, by name.
+    async fn tags(&self) -> StoreResult<Vec<TagCount>>;
+
+    /// Casts or changes `voter`'s vote on a live quote, or retracts it when
+    /// `vote` is `None`, and returns the quote's new rating.
+    async fn vote(&self, id: Uuid, voter: &str, vote: Option<Vote>) -> StoreResult<Option<Rating>>;
+
+    /// Ratings of the given quotes; quotes without votes are left out.
+    async fn ratings(&self, ids: &[Uuid]) -> StoreResult<HashMap<Uuid, Rating>>;
+
+    /// Live quotes with at least one vote, highest [`Rating::score`] first.
+    async fn top(&self, offset: i64, limit: i64) -> StoreResult<Vec<RatedQuote>>;
+
+    /// Kept events after event `after`, oldest first; at most the latest
+    /// [`EVENT_BACKLOG`] of them.
+    async fn events_since(&self, after: i64) -> StoreResult<Vec<QuoteEvent>>;
+
+    /// Live feed of quotes being created, updated, deleted or restored.
+    fn subscribe(&self) -> broadcast::Receiver<QuoteEvent>;
+
+    /// Appends to the audit log, which is never changed or cleared, not even
+    /// by [`reset`](QuoteStore::reset).
+    async fn append_audit(&self, record: &AuditRecord) -> StoreResult<AuditEntry>;
+
+    /// Matching audit entries, newest first.
+    async fn audit(&self, query: &AuditQuery) -> StoreResult<Vec<AuditEntry>>;
+
+    /// Inserts quotes as given, keeping their ids, creation times and
+    /// versions, all in one transaction.
+    ///
+    /// Nothing is written if any of the ids is already taken, including by a
+    /// soft-deleted quote; the taken ids are returned instead.
+    async fn import(&self, quotes: &[Quote], actor: Option<&str>) -> StoreResult<Vec<Uuid>>;
+
+    async fn reset(&self, actor: Option<&str>) -> StoreResult<()>;
+
+    /// All versions of a live quote, oldest first.
+    async fn history(&self, id: Uuid) -> StoreResult<Option<Vec<QuoteVersion>>>;
+
+    async fn version(&self, id: Uuid, version: i32) -> StoreResult<Option<QuoteVersion>>;
+
+    /// Restores the text of an earlier version as a new version.
+    /// `expected_version` works as for [`update`](QuoteStore::update).
+    async fn revert(
+        &self,
+        id: Uuid,
+        version: i32,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>>;
+}
+
+/// Connects to the backend named by `url` and runs its migrations.
+///
+/// * `postgres://...` / `postgresql://...` uses Postgres
+/// * `sqlite:...` uses SQLite, creating the file if needed
+/// * `memory` uses a process-local map
+pub async fn connect(url: &str) -> Result<Arc<dyn QuoteStore>, Box<dyn Error + Send + Sync>> {
+    if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        Ok(Arc::new(PgQuoteStore::connect(url).await?))
+    } else if url.starts_with("sqlite:") {
+        Ok(Arc::new(SqliteQuoteStore::connect(url).await?))
+    } else if url == "memory" {
+        Ok(Arc::new(MemoryQuoteStore::default()))
+    } else {
+        Err(format!("unsupported quote store url: {url}").into())
+    }
+}
+
+/// Spawns a task that periodically purges quotes soft-deleted longer than
+/// `retention` ago. A zero retention purges deleted quotes every
+/// [`MIN_PURGE_INTERVAL`].
+pub fn spawn_purge_job(quotes: Arc<dyn QuoteStore>, retention: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let period = PURGE_INTERVAL.min(retention).max(MIN_PURGE_INTERVAL);
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            let Ok(retention) = chrono::Duration::from_std(retention) else {
+                return;
+            };
+            match quotes.purge_deleted_before(Utc::now() - retention).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!("purged {purged} deleted quotes"),
+                Err(err) => tracing::error!("failed to purge deleted quotes: {err}"),
+            }
+        }
+    })
+}
+
+/// Term-matching search for backends without a full-text index.
+///
+/// Every search term must occur in the author or quote. Quotes are ranked by
+/// how often the terms occur, author hits counting double.
+fn naive_search(quotes: impl IntoIterator<Item = Quote>, query: &SearchQuery) -> Vec<SearchHit> {
+    let terms: Vec<String> = query
+        .text
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect();
+
+    let mut hits: Vec<SearchHit> = quotes
+        .into_iter()
+        .filter(|quote| quote.deleted_at.is_none())
+        .filter(|quote| {
+            query
+                .author
+                .as_ref()
+                .is_none_or(|author| quote.author.to_lowercase() == author.to_lowercase())
+        })
+        .filter_map(|quote| {
+            let author = quote.author.to_lowercase();
+            let text = quote.quote.to_lowercase();
+            let mut rank = 0.0;
+            for term in &terms {
+                let count =
+                    2 * author.matches(term.as_str()).count() + text.matches(term.as_str()).count();
+                if count == 0 {
+                    return None;
+                }
+                rank += count as f32;
+            }
+            Some(SearchHit {
+                snippet: highlight(&quote.quote, &terms),
+                quote,
+                rank,
+            })
+        })
+        .collect();
+
+    hits.sort_by(|a, b| {
+        b.rank
+            .total_cmp(&a.rank)
+            .then_with(|| (a.quote.created_at, a.quote.id).cmp(&(b.quote.created_at, b.quote.id)))
+    });
+    hits.into_iter()
+        .skip(query.offset.max(0) as usize)
+        .take(query.limit.max(0) as usize)
+        .collect()
+}
+
+/// Leaderboard order for backends ranking in Rust: best score first, then
+/// most votes, then oldest.
+fn rank_top(mut rated: Vec<RatedQuote>, offset: i64, limit: i64) -> Vec<RatedQuote> {
+    rated.sort_by(|a, b| {
+        let votes = |r: &RatedQuote| r.rating.upvotes + r.rating.downvotes;
+        b.rating
+            .score()
+            .total_cmp(&a.rating.score())
+            .then_with(|| votes(b).cmp(&votes(a)))
+            .then_with(|| (a.quote.created_at, a.quote.id).cmp(&(b.quote.created_at, b.quote.id)))
+    });
+    rated
+        .into_iter()
+        .skip(offset.max(0) as usize)
+        .take(limit.max(0) as usize)
+        .collect()
+}
+
+/// Escapes `text` for HTML and wraps case-insensitive occurrences of `terms`
+/// in `<mark>` tags.
+fn highlight(text: &str, terms: &[String]) -> String {
+    let lower = text.to_lowercase();
+    // Lowercasing can change byte lengths, in which case offsets would no
+    // longer line up with the original text.
+    if lower.len() != text.len() {
+        return escape_html(text);
+    }
+    let mut marked = vec![false; text.len()];
+    for term in terms.iter().filter(|term| !term.is_empty()) {
+        for (start, _) in lower.match_indices(term.as_str()) {
+            marked[start..start + term.len()].fill(true);
+        }
+    }
+
+    let mut snippet = String::with_capacity(text.len());
+    let mut run = String::new();
+    let mut open = false;
+    for (i, c) in text.char_indices() {
+        if marked[i] != open {
+            snippet.push_str(&escape_html(&run));
+            snippet.push_str(if marked[i] { "<mark>" } else { "</mark>" });
+            run.clear();
+            open = marked[i];
+        }
+        run.push(c);
+    }
+    snippet.push_str(&escape_html(&run));
+    if open {
+        snippet.push_str("</mark>");
+    }
+    snippet
+}
@@ -0,0 +1,973 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{migrate::MigrateError, postgres::PgListener, PgPool, Postgres, Transaction};
+use tera::escape_html;
+use tokio::{sync::broadcast, task::JoinHandle};
+use uuid::Uuid;
+
+use super::{
+    normalize_author, AuditEntry, AuditQuery, AuditRecord, AuthorStats, EventBus, Quote,
+    QuoteEvent, QuoteEventKind, QuoteStore, QuoteVersion, RatedQuote, Rating, SearchHit,
+    SearchQuery, StoreResult, TagCount, TagFilter, Vote, EVENT_BACKLOG, PURGE_ACTOR,
+};
+
+/// Channel the `quote_events` trigger notifies with new event ids.
+const EVENT_CHANNEL: &str = "quote_events";
+const LISTEN_RETRY: Duration = Duration::from_secs(5);
+/// Private-use characters `ts_headline` marks matches with, so that the
+/// quote can be escaped before they are turned into `<mark>` tags. Quotes
+/// have them stripped first, so only the markers remain.
+const MARK_START: char = '\u{E000}';
+const MARK_STOP: char = '\u{E001}';
+const MARKS: &str = "\u{E000}\u{E001}";
+const HEADLINE_OPTIONS: &str = "StartSel=\"\u{E000}\", StopSel=\"\u{E001}\", HighlightAll=true";
+
+#[derive(Debug, Clone)]
+pub struct PgQuoteStore {
+    pool: PgPool,
+    /// Fed by a listener on [`EVENT_CHANNEL`], so changes made by other
+    /// instances reach this one's subscribers too.
+    events: EventBus,
+    _listener: Arc<Listener>,
+}
+
+/// The task running [`forward_events`], stopped with the last store clone.
+#[derive(Debug)]
+struct Listener(JoinHandle<()>);
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+struct EventRow {
+    id: i64,
+    kind: String,
+    quote_id: Uuid,
+    author: String,
+    quote: String,
+    quote_created_at: DateTime<Utc>,
+    version: i32,
+    deleted_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+impl EventRow {
+    fn into_event(self) -> Option<QuoteEvent> {
+        Some(QuoteEvent {
+            id: self.id,
+            kind: QuoteEventKind::parse(&self.kind)?,
+            quote: Quote {
+                id: self.quote_id,
+                author: self.author,
+                quote: self.quote,
+                created_at: self.quote_created_at,
+                version: self.version,
+                deleted_at: self.deleted_at,
+            },
+            created_at: self.created_at,
+        })
+    }
+}
+
+/// Ids of the latest events forwarded, or already there when listening
+/// started. Ids are taken when events are written but may commit out of
+/// order, so a late one is told apart by the set rather than by the highest
+/// id seen.
+#[derive(Default)]
+struct Forwarded(BTreeSet<i64>);
+
+impl Forwarded {
+    async fn existing(pool: &PgPool) -> StoreResult<Self> {
+        let ids = sqlx::query_scalar!(
+            "SELECT id FROM quote_events ORDER BY id DESC LIMIT $1",
+            EVENT_BACKLOG as i64
+        )
+        .fetch_all(pool)
+        .await?;
+        let mut forwarded = Forwarded::default();
+        for id in ids {
+            forwarded.insert(id);
+        }
+        Ok(forwarded)
+    }
+
+    fn contains(&self, id: i64) -> bool {
+        self.0.contains(&id)
+    }
+
+    /// Notes `id`, returning whether it is new.
+    fn insert(&mut self, id: i64) -> bool {
+        let new = self.0.insert(id);
+        if self.0.len() > EVENT_BACKLOG {
+            self.0.pop_first();
+        }
+        new
+    }
+
+    /// Where catching up starts: far enough back to pick up events that
+    /// committed after later ones.
+    fn catch_up_after(&self) -> i64 {
+        self.0.last().map_or(0, |&last| last - EVENT_BACKLOG as i64)
+    }
+}
+
+/// Forwards events notified by the `quote_events` trigger to `events`,
+/// reconnecting for as long as the process runs.
+async fn forward_events(pool: PgPool, events: EventBus) {
+    let mut forwarded = None;
+    loop {
+        if let Err(err) = listen(&pool, &events, &mut forwarded).await {
+            tracing::error!("quote event listener failed: {err}");
+        }
+        tokio::time::sleep(LISTEN_RETRY).await;
+    }
+}
+
+/// Listens until the connection fails, first forwarding the events missed
+/// while not listening.
+async fn listen(
+    pool: &PgPool,
+    events: &EventBus,
+    forwarded: &mut Option<Forwarded>,
+) -> StoreResult<()> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    let forwarded = match forwarded {
+        Some(forwarded) => forwarded,
+        None => forwarded.insert(Forwarded::existing(pool).await?),
+    };
+    listener.listen(EVENT_CHANNEL).await?;
+
+    let missed = sqlx::query_as!(
+        EventRow,
+        "SELECT * FROM quote_events WHERE id > $1 ORDER BY id",
+        forwarded.catch_up_after()
+    )
+    .fetch_all(pool)
+    .await?;
+    for event in missed.into_iter().filter_map(EventRow::into_event) {
+        if forwarded.insert(event.id) {
+            events.forward(event);
+        }
+    }
+
+    loop {
+        let notification = listener.recv().await?;
+        let Ok(id) = notification.payload().parse::<i64>() else {
+            continue;
+        };
+        // Already forwarded by the catch-up above.
+        if forwarded.contains(id) {
+            continue;
+        }
+        let row = sqlx::query_as!(EventRow, "SELECT * FROM quote_events WHERE id = $1", id)
+            .fetch_optional(pool)
+            .await?;
+        if let Some(event) = row.and_then(EventRow::into_event) {
+            forwarded.insert(event.id);
+            events.forward(event);
+        }
+    }
+}
+
+/// Escapes a headline made with [`HEADLINE_OPTIONS`] for HTML and marks its
+/// matches.
+fn snippet(headline: &str) -> String {
+    let mut snippet = String::with_capacity(headline.len());
+    let mut last = 0;
+    for (at, marker) in headline.match_indices([MARK_START, MARK_STOP]) {
+        snippet.push_str(&escape_html(&headline[last..at]));
+        snippet.push_str(if marker.starts_with(MARK_START) {
+            "<mark>"
+        } else {
+            "</mark>"
+        });
+        last = at + marker.len();
+    }
+    snippet.push_str(&escape_html(&headline[last..]));
+    snippet
+}
+
+async fn record_version(tx: &mut Transaction<'_, Postgres>, quote: &Quote) -> StoreResult<()> {
+    sqlx::query!(
+        "INSERT INTO quote_versions (quote_id, version, author, quote) VALUES ($1, $2, $3, $4)",
+        quote.id,
+        quote.version,
+        quote.author,
+        quote.quote
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Appends to the audit log as part of `tx`.
+async fn append_audit(
+    tx: &mut Transaction<'_, Postgres>,
+    record: &AuditRecord,
+) -> StoreResult<AuditEntry> {
+    let row = sqlx::query!(
+        "INSERT INTO quote_audit (action, quote_id, before, after, actor) VALUES ($1, $2, $3, $4, $5) RETURNING id, created_at",
+        record.action,
+        record.quote_id,
+        record.before,
+        record.after,
+        record.actor
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    Ok(AuditEntry {
+        id: row.id,
+        record: record.clone(),
+        created_at: row.created_at,
+    })
+}
+
+/// Replaces author and quote of a live quote as part of `tx`, audited as
+/// `action`.
+async fn update(
+    tx: &mut Transaction<'_, Postgres>,
+    action: &str,
+    id: Uuid,
+    author: &str,
+    quote: &str,
+    expected_version: Option<i32>,
+    actor: Option<&str>,
+) -> StoreResult<Option<Quote>> {
+    let Some(before) = sqlx::query_as!(
+        Quote,
+        "SELECT id, author, quote, created_at, version, deleted_at FROM quotes WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        id
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let result = sqlx::query_as!(Quote, "UPDATE quotes SET (author, quote, version) = ($1, $2, version+1) WHERE id = $3 AND deleted_at IS NULL AND ($4::INT IS NULL OR version = $4) RETURNING id, author, quote, created_at, version, deleted_at", author, quote, id, expected_version)
+        .fetch_optional(&mut **tx)
+        .await?;
+    if let Some(result) = &result {
+        // Filed only once the update went through, so refused ones leave no
+        // author behind.
+        let author_id = author_id(tx, author).await?;
+        sqlx::query!(
+            "UPDATE quotes SET author_id = $1 WHERE id = $2",
+            author_id,
+            id
+        )
+        .execute(&mut **tx)
+        .await?;
+        record_version(tx, result).await?;
+        let record = AuditRecord::quote(action, actor, Some(&before), Some(result));
+        append_audit(tx, &record).await?;
+    }
+    Ok(result)
+}
+
+/// Id of the author `name` normalizes to, created on first use.
+async fn author_id(tx: &mut Transaction<'_, Postgres>, name: &str) -> StoreResult<Uuid> {
+    // The no-op update makes RETURNING yield the id of an existing author too.
+    sqlx::query_scalar!(
+        "INSERT INTO authors (name, normalized_name) VALUES ($1, $2) ON CONFLICT (normalized_name) DO UPDATE SET normalized_name = EXCLUDED.normalized_name RETURNING id",
+        name.trim(),
+        normalize_author(name)
+    )
+    .fetch_one(&mut **tx)
+    .await
+}
+
+/// Files quotes from before there were authors, oldest first so each author
+/// is named after their first quote.
+async fn file_authors(pool: &PgPool) -> StoreResult<()> {
+    let mut tx = pool.begin().await?;
+    let unfiled = sqlx::query!(
+        "SELECT id, author FROM quotes WHERE author_id IS NULL ORDER BY created_at, id FOR UPDATE"
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for quote in unfiled {
+        let author_id = author_id(&mut tx, &quote.author).await?;
+        sqlx::query!(
+            "UPDATE quotes SET author_id = $1 WHERE id = $2",
+            author_id,
+            quote.id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// Tags of a quote, sorted.
+async fn quote_tags(tx: &mut Transaction<'_, Postgres>, id: Uuid) -> StoreResult<Vec<String>> {
+    sqlx::query_scalar!(
+        "SELECT tags.name FROM quote_tags JOIN tags ON tags.id = quote_tags.tag_id WHERE quote_tags.quote_id = $1 ORDER BY tags.name",
+        id
+    )
+    .fetch_all(&mut **tx)
+    .await
+}
+
+/// Whether a quote exists and isn't soft-deleted, locking it until `tx` ends.
+async fn lock_live(tx: &mut Transaction<'_, Postgres>, id: Uuid) -> StoreResult<bool> {
+    let quote = sqlx::query_scalar!(
+        "SELECT id FROM quotes WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        id
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    Ok(quote.is_some())
+}
+
+impl PgQuoteStore {
+    /// Wraps an already migrated pool and starts listening for events, so
+    /// subscribers miss none made from then on. Must be called within a
+    /// Tokio runtime.
+    pub fn new(pool: PgPool) -> Self {
+        let events = EventBus::default();
+        let listener = tokio::spawn(forward_events(pool.clone(), events.clone()));
+        PgQuoteStore {
+            pool,
+            events,
+            _listener: Arc::new(Listener(listener)),
+        }
+    }
+
+    pub async fn connect(url: &str) -> Result<Self, MigrateError> {
+        let pool = PgPool::connect(url).await?;
+        Self::migrate(&pool).await?;
+        Ok(Self::new(pool))
+    }
+
+    pub async fn migrate(pool: &PgPool) -> Result<(), MigrateError> {
+        sqlx::migrate!("./migrations").run(pool).await?;
+        file_authors(pool).await.map_err(MigrateError::Execute)
+    }
+}
+
+#[async_trait]
+impl QuoteStore for PgQuoteStore {
+    async fn insert(
+        &self,
+        id: Uuid,
+        author: &str,
+        quote: &str,
+        actor: Option<&str>,
+    ) -> StoreResult<Quote> {
+        let mut tx = self.pool.begin().await?;
+        let author_id = author_id(&mut tx, author).await?;
+        let result = sqlx::query_as!(
+            Quote,
+            "INSERT INTO quotes (id, author, author_id, quote) VALUES ($1, $2, $3, $4) RETURNING id, author, quote, created_at, version, deleted_at",
+            id,
+            author,
+            author_id,
+            quote
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        record_version(&mut tx, &result).await?;
+        let record = AuditRecord::quote("create", actor, None, Some(&result));
+        append_audit(&mut tx, &record).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn get(&self, id: Uuid) -> StoreResult<Option<Quote>> {
+        sqlx::query_as!(
+            Quote,
+            "SELECT id, author, quote, created_at, version, deleted_at FROM quotes WHERE id = $1 AND deleted_at IS NULL",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn update(
+        &self,
+        id: Uuid,
+        author: &str,
+        quote: &str,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut tx = self.pool.begin().await?;
+        let result = update(
+            &mut tx,
+            "update",
+            id,
+            author,
+            quote,
+            expected_version,
+            actor,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn remove(
+        &self,
+        id: Uuid,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query_as!(
+            Quote,
+            "UPDATE quotes SET deleted_at = now() WHERE id = $1 AND deleted_at IS NULL AND ($2::INT IS NULL OR version = $2) RETURNING id, author, quote, created_at, version, deleted_at",
+            id,
+            expected_version
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(result) = &result {
+            let record = AuditRecord::quote("delete", actor, Some(result), None);
+            append_audit(&mut tx, &record).await?;
+        }
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn get_deleted(&self, id: Uuid) -> StoreResult<Option<Quote>> {
+        sqlx::query_as!(
+            Quote,
+            "SELECT id, author, quote, created_at, version, deleted_at FROM quotes WHERE id = $1 AND deleted_at IS NOT NULL",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn restore(
+        &self,
+        id: Uuid,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query_as!(
+            Quote,
+            "UPDATE quotes SET deleted_at = NULL WHERE id = $1 AND deleted_at IS NOT NULL AND ($2::INT IS NULL OR version = $2) RETURNING id, author, quote, created_at, version, deleted_at",
+            id,
+            expected_version
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(result) = &result {
+            let record = AuditRecord::quote("restore", actor, None, Some(result));
+            append_audit(&mut tx, &record).await?;
+        }
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn purge(
+        &self,
+        id: Uuid,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query_as!(
+            Quote,
+            "DELETE FROM quotes WHERE id = $1 AND deleted_at IS NOT NULL AND ($2::INT IS NULL OR version = $2) RETURNING id, author, quote, created_at, version, deleted_at",
+            id,
+            expected_version
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(result) = &result {
+            let record = AuditRecord::quote("purge", actor, Some(result), None);
+            append_audit(&mut tx, &record).await?;
+        }
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> StoreResult<u64> {
+        let mut tx = self.pool.begin().await?;
+        let purged = sqlx::query_as!(
+            Quote,
+            "DELETE FROM quotes WHERE deleted_at < $1 RETURNING id, author, quote, created_at, version, deleted_at",
+            cutoff
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let (ids, befores): (Vec<_>, Vec<_>) = purged
+            .iter()
+            .map(|quote| AuditRecord::quote("purge", Some(PURGE_ACTOR), Some(quote), None))
+            .map(|record| (record.quote_id, record.before))
+            .unzip();
+        sqlx::query!(
+            "INSERT INTO quote_audit (action, quote_id, before, actor) SELECT 'purge', purged.quote_id, purged.before, $3 FROM UNNEST($1::UUID[], $2::JSONB[]) AS purged (quote_id, before)",
+            &ids as &[Option<Uuid>],
+            &befores as &[Option<serde_json::Value>],
+            PURGE_ACTOR
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        // Events are kept by count rather than age, so resumes and the
+        // listener find them however short the quote retention is.
+        sqlx::query!(
+            "DELETE FROM quote_events WHERE id <= (SELECT id FROM quote_events ORDER BY id DESC OFFSET $1 LIMIT 1)",
+            EVENT_BACKLOG as i64
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(purged.len() as u64)
+    }
+
+    async fn list(&self, offset: i64, limit: i64, tags: &TagFilter) -> StoreResult<Vec<Quote>> {
+        sqlx::query_as!(
+            Quote,
+            r#"SELECT id, author, quote, created_at, version, deleted_at FROM quotes
+            WHERE deleted_at IS NULL
+                AND (
+                    SELECT count(*) FROM quote_tags JOIN tags ON tags.id = quote_tags.tag_id
+                    WHERE quote_tags.quote_id = quotes.id AND tags.name = ANY($3)
+                ) >= $4
+            ORDER BY created_at, id
+            LIMIT $1 OFFSET $2"#,
+            limit,
+            offset,
+            &tags.tags,
+            tags.min_matches()
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn list_after(
+        &self,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> StoreResult<Vec<Quote>> {
+        let (created_at, id) = after.unzip();
+        sqlx::query_as!(
+            Quote,
+            r#"SELECT id, author, quote, created_at, version, deleted_at FROM quotes
+            WHERE deleted_at IS NULL
+                AND ($1::TIMESTAMPTZ IS NULL OR (created_at, id) > ($1, $2::UUID))
+            ORDER BY created_at, id
+            LIMIT $3"#,
+            created_at,
+            id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count(&self) -> StoreResult<i64> {
+        sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM quotes WHERE deleted_at IS NULL"#)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    async fn search(&self, query: &SearchQuery) -> StoreResult<Vec<SearchHit>> {
+        let rows = sqlx::query!(
+            r#"SELECT id, author, quote, created_at, version, deleted_at,
+                ts_rank(search, tsquery) AS "rank!",
+                ts_headline('english', translate(quote, $8, ''), tsquery, $7) AS "snippet!"
+            FROM quotes, websearch_to_tsquery('english', coalesce($1, '')) AS tsquery
+            WHERE deleted_at IS NULL
+                AND ($1::TEXT IS NULL OR search @@ tsquery)
+                AND ($2::TEXT IS NULL OR lower(author) = lower($2))
+                AND (
+                    SELECT count(*) FROM quote_tags JOIN tags ON tags.id = quote_tags.tag_id
+                    WHERE quote_tags.quote_id = quotes.id AND tags.name = ANY($5)
+                ) >= $6
+            ORDER BY 7 DESC, created_at, id
+            LIMIT $3 OFFSET $4"#,
+            query.text,
+            query.author,
+            query.limit,
+            query.offset,
+            &query.tags.tags,
+            query.tags.min_matches(),
+            HEADLINE_OPTIONS,
+            MARKS
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| SearchHit {
+                quote: Quote {
+                    id: row.id,
+                    author: row.author,
+                    quote: row.quote,
+                    created_at: row.created_at,
+                    version: row.version,
+                    deleted_at: row.deleted_at,
+                },
+                rank: row.rank,
+                snippet: snippet(&row.snippet),
+            })
+            .collect())
+    }
+
+    async fn authors(&self) -> StoreResult<Vec<AuthorStats>> {
+        sqlx::query_as!(
+            AuthorStats,
+            r#"SELECT authors.id, authors.name,
+                count(*) AS "quote_count!",
+                min(quotes.created_at) AS "first_quote_at!",
+                max(quotes.created_at) AS "last_quote_at!"
+            FROM authors
+            JOIN quotes ON quotes.author_id = authors.id AND quotes.deleted_at IS NULL
+            GROUP BY authors.id
+            ORDER BY authors.normalized_name"#
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn quotes_by_author(&self, author_id: Uuid) -> StoreResult<Option<Vec<Quote>>> {
+        let known = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM authors WHERE id = $1) AS "exists!""#,
+            author_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        if !known {
+            return Ok(None);
+        }
+        sqlx::query_as!(
+            Quote,
+            "SELECT id, author, quote, created_at, version, deleted_at FROM quotes WHERE author_id = $1 AND deleted_at IS NULL ORDER BY created_at, id",
+            author_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map(Some)
+    }
+
+    async fn tag(&self, id: Uuid, tag: &str) -> StoreResult<Option<Vec<String>>> {
+        let mut tx = self.pool.begin().await?;
+        if !lock_live(&mut tx, id).await? {
+            return Ok(None);
+        }
+        // The no-op update makes RETURNING yield the id of an existing tag too.
+        sqlx::query!(
+            "WITH tag AS (INSERT INTO tags (name) VALUES ($2) ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name RETURNING id) INSERT INTO quote_tags (quote_id, tag_id) SELECT $1, id FROM tag ON CONFLICT DO NOTHING",
+            id,
+            tag
+        )
+        .execute(&mut *tx)
+        .await?;
+        let tags = quote_tags(&mut tx, id).await?;
+        tx.commit().await?;
+        Ok(Some(tags))
+    }
+
+    async fn untag(&self, id: Uuid, tag: &str) -> StoreResult<Option<Vec<String>>> {
+        let mut tx = self.pool.begin().await?;
+        if !lock_live(&mut tx, id).await? {
+            return Ok(None);
+        }
+        sqlx::query!(
+            "DELETE FROM quote_tags USING tags WHERE quote_tags.tag_id = tags.id AND quote_tags.quote_id = $1 AND tags.name = $2",
+            id,
+            tag
+        )
+        .execute(&mut *tx)
+        .await?;
+        let tags = quote_tags(&mut tx, id).await?;
+        tx.commit().await?;
+        Ok(Some(tags))
+    }
+
+    async fn tags(&self) -> StoreResult<Vec<TagCount>> {
+        sqlx::query_as!(
+            TagCount,
+            r#"SELECT tags.name, count(*) AS "quote_count!"
+            FROM tags
+            JOIN quote_tags ON quote_tags.tag_id = tags.id
+            JOIN quotes ON quotes.id = quote_tags.quote_id AND quotes.deleted_at IS NULL
+            GROUP BY tags.name
+            ORDER BY tags.name"#
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn vote(&self, id: Uuid, voter: &str, vote: Option<Vote>) -> StoreResult<Option<Rating>> {
+        let mut tx = self.pool.begin().await?;
+        if !lock_live(&mut tx, id).await? {
+            return Ok(None);
+        }
+        match vote {
+            Some(vote) => {
+                sqlx::query!(
+                    "INSERT INTO quote_votes (quote_id, voter, value) VALUES ($1, $2, $3) ON CONFLICT (quote_id, voter) DO UPDATE SET value = EXCLUDED.value, voted_at = NOW()",
+                    id,
+                    voter,
+                    vote.value()
+                )
+                .execute(&mut *tx)
+                .await?
+            }
+            None => {
+                sqlx::query!(
+                    "DELETE FROM quote_votes WHERE quote_id = $1 AND voter = $2",
+                    id,
+                    voter
+                )
+                .execute(&mut *tx)
+                .await?
+            }
+        };
+        let rating = sqlx::query_as!(
+            Rating,
+            r#"SELECT count(*) FILTER (WHERE value = 1) AS "upvotes!",
+                count(*) FILTER (WHERE value = -1) AS "downvotes!"
+            FROM quote_votes WHERE quote_id = $1"#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(Some(rating))
+    }
+
+    async fn ratings(&self, ids: &[Uuid]) -> StoreResult<HashMap<Uuid, Rating>> {
+        let rows = sqlx::query!(
+            r#"SELECT quote_id,
+                count(*) FILTER (WHERE value = 1) AS "upvotes!",
+                count(*) FILTER (WHERE value = -1) AS "downvotes!"
+            FROM quote_votes WHERE quote_id = ANY($1)
+            GROUP BY quote_id"#,
+            ids
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let rating = Rating {
+                    upvotes: row.upvotes,
+                    downvotes: row.downvotes,
+                };
+                (row.quote_id, rating)
+            })
+            .collect())
+    }
+
+    async fn top(&self, offset: i64, limit: i64) -> StoreResult<Vec<RatedQuote>> {
+        // Same formula as `Rating::score`, with z = 1.96.
+        let rows = sqlx::query!(
+            r#"SELECT id, author, quote, created_at, version, deleted_at,
+                ratings.upvotes AS "upvotes!", ratings.downvotes AS "downvotes!"
+            FROM quotes
+            JOIN (
+                SELECT quote_id,
+                    count(*) FILTER (WHERE value = 1) AS upvotes,
+                    count(*) FILTER (WHERE value = -1) AS downvotes,
+                    count(*)::FLOAT8 AS n,
+                    count(*) FILTER (WHERE value = 1)::FLOAT8 / count(*) AS p
+                FROM quote_votes GROUP BY quote_id
+            ) AS ratings ON ratings.quote_id = quotes.id
+            WHERE deleted_at IS NULL
+            ORDER BY (p + 1.9208 / n - 1.96 * sqrt((p * (1 - p) + 0.9604 / n) / n)) / (1 + 3.8416 / n) DESC,
+                n DESC, created_at, id
+            LIMIT $1 OFFSET $2"#,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| RatedQuote {
+                quote: Quote {
+                    id: row.id,
+                    author: row.author,
+                    quote: row.quote,
+                    created_at: row.created_at,
+                    version: row.version,
+                    deleted_at: row.deleted_at,
+                },
+                rating: Rating {
+                    upvotes: row.upvotes,
+                    downvotes: row.downvotes,
+                },
+            })
+            .collect())
+    }
+
+    async fn events_since(&self, after: i64) -> StoreResult<Vec<QuoteEvent>> {
+        let rows = sqlx::query_as!(
+            EventRow,
+            "SELECT * FROM (SELECT * FROM quote_events WHERE id > $1 ORDER BY id DESC LIMIT $2) AS latest ORDER BY id",
+            after,
+            EVENT_BACKLOG as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().filter_map(EventRow::into_event).collect())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<QuoteEvent> {
+        self.events.subscribe()
+    }
+
+    async fn append_audit(&self, record: &AuditRecord) -> StoreResult<AuditEntry> {
+        let mut tx = self.pool.begin().await?;
+        let entry = append_audit(&mut tx, record).await?;
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    async fn audit(&self, query: &AuditQuery) -> StoreResult<Vec<AuditEntry>> {
+        let rows = sqlx::query!(
+            r#"SELECT id, action, quote_id, before, after, actor, created_at FROM quote_audit
+            WHERE ($1::UUID IS NULL OR quote_id = $1)
+                AND ($2::TIMESTAMPTZ IS NULL OR created_at >= $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR created_at < $3)
+            ORDER BY id DESC
+            LIMIT $4 OFFSET $5"#,
+            query.quote_id,
+            query.since,
+            query.until,
+            query.limit,
+            query.offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| AuditEntry {
+                id: row.id,
+                record: AuditRecord {
+                    action: row.action,
+                    quote_id: row.quote_id,
+                    before: row.before,
+                    after: row.after,
+                    actor: row.actor,
+                },
+                created_at: row.created_at,
+            })
+            .collect())
+    }
+
+    async fn import(&self, quotes: &[Quote], actor: Option<&str>) -> StoreResult<Vec<Uuid>> {
+        let mut tx = self.pool.begin().await?;
+        let mut taken = Vec::new();
+        for quote in quotes {
+            let author_id = author_id(&mut tx, &quote.author).await?;
+            let inserted = sqlx::query!(
+                "INSERT INTO quotes (id, author, author_id, quote, created_at, version) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (id) DO NOTHING",
+                quote.id,
+                quote.author,
+                author_id,
+                quote.quote,
+                quote.created_at,
+                quote.version
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if inserted == 0 {
+                taken.push(quote.id);
+            } else {
+                record_version(&mut tx, quote).await?;
+            }
+        }
+        // Dropping the transaction rolls it back.
+        if taken.is_empty() {
+            let (ids, afters): (Vec<_>, Vec<_>) = quotes
+                .iter()
+                .map(|quote| AuditRecord::quote("import", actor, None, Some(quote)))
+                .map(|record| (record.quote_id, record.after))
+                .unzip();
+            sqlx::query!(
+                "INSERT INTO quote_audit (action, quote_id, after, actor) SELECT 'import', imported.quote_id, imported.after, $3 FROM UNNEST($1::UUID[], $2::JSONB[]) AS imported (quote_id, after)",
+                &ids as &[Option<Uuid>],
+                &afters as &[Option<serde_json::Value>],
+                actor
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+        }
+        Ok(taken)
+    }
+
+    async fn reset(&self, actor: Option<&str>) -> StoreResult<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            "TRUNCATE quotes, quote_versions, authors, quote_tags, tags, quote_votes, quote_events"
+        )
+        .execute(&mut *tx)
+        .await?;
+        append_audit(&mut tx, &AuditRecord::book("reset", actor)).await?;
+        tx.commit().await
+    }
+
+    async fn history(&self, id: Uuid) -> StoreResult<Option<Vec<QuoteVersion>>> {
+        let versions = sqlx::query_as!(
+            QuoteVersion,
+            "SELECT quote_versions.* FROM quote_versions JOIN quotes ON quotes.id = quote_versions.quote_id AND quotes.deleted_at IS NULL WHERE quote_id = $1 ORDER BY quote_versions.version",
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        // Every quote has at least its first version.
+        Ok(Some(versions).filter(|versions| !versions.is_empty()))
+    }
+
+    async fn version(&self, id: Uuid, version: i32) -> StoreResult<Option<QuoteVersion>> {
+        sqlx::query_as!(
+            QuoteVersion,
+            "SELECT quote_versions.* FROM quote_versions JOIN quotes ON quotes.id = quote_versions.quote_id AND quotes.deleted_at IS NULL WHERE quote_id = $1 AND quote_versions.version = $2",
+            id,
+            version
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn revert(
+        &self,
+        id: Uuid,
+        version: i32,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut tx = self.pool.begin().await?;
+        let Some(old) = sqlx::query_as!(
+            QuoteVersion,
+            "SELECT * FROM quote_versions WHERE quote_id = $1 AND version = $2",
+            id,
+            version
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(None);
+        };
+        let result = update(
+            &mut tx,
+            "revert",
+            id,
+            &old.author,
+            &old.quote,
+            expected_version,
+            actor,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+}
@@ -0,0 +1,706 @@
+use std::{collections::HashMap, str::FromStr};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{
+    migrate::MigrateError,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    types::Json,
+    Sqlite, SqlitePool, Transaction,
+};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use super::{
+    naive_search, normalize_author, rank_top, AuditEntry, AuditQuery, AuditRecord, AuthorStats,
+    EventBus, Quote, QuoteEvent, QuoteEventKind, QuoteStore, QuoteVersion, RatedQuote, Rating,
+    SearchHit, SearchQuery, StoreResult, TagCount, TagFilter, Vote, PURGE_ACTOR,
+};
+
+#[derive(Debug, Clone)]
+pub struct SqliteQuoteStore {
+    pool: SqlitePool,
+    /// SQLite can't notify other processes, so events stay in this one.
+    events: EventBus,
+}
+
+async fn record_version(tx: &mut Transaction<'_, Sqlite>, quote: &Quote) -> StoreResult<()> {
+    sqlx::query(
+        "INSERT INTO quote_versions (quote_id, version, author, quote, created_at) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(quote.id)
+    .bind(quote.version)
+    .bind(&quote.author)
+    .bind(&quote.quote)
+    .bind(Utc::now())
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Appends to the audit log as part of `tx`.
+async fn append_audit(
+    tx: &mut Transaction<'_, Sqlite>,
+    record: &AuditRecord,
+) -> StoreResult<AuditEntry> {
+    let created_at = Utc::now();
+    let id = sqlx::query_scalar(
+        "INSERT INTO quote_audit (action, quote_id, before, after, actor, created_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+    )
+    .bind(&record.action)
+    .bind(record.quote_id)
+    .bind(record.before.as_ref().map(Json))
+    .bind(record.after.as_ref().map(Json))
+    .bind(&record.actor)
+    .bind(created_at)
+    .fetch_one(&mut **tx)
+    .await?;
+    Ok(AuditEntry {
+        id,
+        record: record.clone(),
+        created_at,
+    })
+}
+
+/// Replaces author and quote of a live quote as part of `tx`, audited as
+/// `action`.
+async fn update(
+    tx: &mut Transaction<'_, Sqlite>,
+    action: &str,
+    id: Uuid,
+    author: &str,
+    quote: &str,
+    expected_version: Option<i32>,
+    actor: Option<&str>,
+) -> StoreResult<Option<Quote>> {
+    let before: Option<Quote> =
+        sqlx::query_as("SELECT * FROM quotes WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(&mut **tx)
+            .await?;
+    let Some(before) = before else {
+        return Ok(None);
+    };
+    let result: Option<Quote> = sqlx::query_as(
+        "UPDATE quotes SET author = $1, quote = $2, version = version + 1 WHERE id = $3 AND deleted_at IS NULL AND ($4 IS NULL OR version = $4) RETURNING *",
+    )
+    .bind(author)
+    .bind(quote)
+    .bind(id)
+    .bind(expected_version)
+    .fetch_optional(&mut **tx)
+    .await?;
+    if let Some(result) = &result {
+        // Filed only once the update went through, so refused ones leave no
+        // author behind.
+        let author_id = author_id(tx, author).await?;
+        sqlx::query("UPDATE quotes SET author_id = $1 WHERE id = $2")
+            .bind(author_id)
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+        record_version(tx, result).await?;
+        let record = AuditRecord::quote(action, actor, Some(&before), Some(result));
+        append_audit(tx, &record).await?;
+    }
+    Ok(result)
+}
+
+#[derive(sqlx::FromRow)]
+struct AuditRow {
+    id: i64,
+    action: String,
+    quote_id: Option<Uuid>,
+    before: Option<Json<serde_json::Value>>,
+    after: Option<Json<serde_json::Value>>,
+    actor: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<AuditRow> for AuditEntry {
+    fn from(row: AuditRow) -> Self {
+        AuditEntry {
+            id: row.id,
+            record: AuditRecord {
+                action: row.action,
+                quote_id: row.quote_id,
+                before: row.before.map(|Json(before)| before),
+                after: row.after.map(|Json(after)| after),
+                actor: row.actor,
+            },
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Id of the author `name` normalizes to, created on first use.
+async fn author_id(tx: &mut Transaction<'_, Sqlite>, name: &str) -> StoreResult<Uuid> {
+    // The no-op update makes RETURNING yield the id of an existing author too.
+    sqlx::query_scalar(
+        "INSERT INTO authors (id, name, normalized_name, created_at) VALUES ($1, $2, $3, $4) ON CONFLICT (normalized_name) DO UPDATE SET normalized_name = excluded.normalized_name RETURNING id",
+    )
+    .bind(Uuid::new_v4())
+    .bind(name.trim())
+    .bind(normalize_author(name))
+    .bind(Utc::now())
+    .fetch_one(&mut **tx)
+    .await
+}
+
+/// Files quotes from before there were authors, oldest first so each author
+/// is named after their first quote.
+async fn file_authors(pool: &SqlitePool) -> StoreResult<()> {
+    let mut tx = pool.begin().await?;
+    let unfiled: Vec<(Uuid, String)> = sqlx::query_as(
+        "SELECT id, author FROM quotes WHERE author_id IS NULL ORDER BY created_at, id",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for (id, author) in unfiled {
+        let author_id = author_id(&mut tx, &author).await?;
+        sqlx::query("UPDATE quotes SET author_id = $1 WHERE id = $2")
+            .bind(author_id)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+/// Condition on `quotes` rows passing a [`TagFilter`], bound as the tags in a
+/// JSON array followed by [`TagFilter::min_matches`].
+const TAG_FILTER: &str = "(SELECT count(*) FROM quote_tags JOIN tags ON tags.id = quote_tags.tag_id WHERE quote_tags.quote_id = quotes.id AND tags.name IN (SELECT value FROM json_each(?))) >= ?";
+
+fn tags_json(tags: &TagFilter) -> String {
+    serde_json::Value::from(tags.tags.clone()).to_string()
+}
+
+/// Tags of a quote, sorted.
+async fn quote_tags(tx: &mut Transaction<'_, Sqlite>, id: Uuid) -> StoreResult<Vec<String>> {
+    sqlx::query_scalar(
+        "SELECT tags.name FROM quote_tags JOIN tags ON tags.id = quote_tags.tag_id WHERE quote_tags.quote_id = $1 ORDER BY tags.name",
+    )
+    .bind(id)
+    .fetch_all(&mut **tx)
+    .await
+}
+
+/// Vote counts by quote, for quotes with at least one vote.
+const RATINGS: &str = "SELECT quote_id, sum(value = 1) AS upvotes, sum(value = -1) AS downvotes FROM quote_votes GROUP BY quote_id";
+
+#[derive(sqlx::FromRow)]
+struct RatingRow {
+    quote_id: Uuid,
+    upvotes: i64,
+    downvotes: i64,
+}
+
+impl From<&RatingRow> for Rating {
+    fn from(row: &RatingRow) -> Self {
+        Rating {
+            upvotes: row.upvotes,
+            downvotes: row.downvotes,
+        }
+    }
+}
+
+async fn is_live(tx: &mut Transaction<'_, Sqlite>, id: Uuid) -> StoreResult<bool> {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM quotes WHERE id = $1 AND deleted_at IS NULL)")
+        .bind(id)
+        .fetch_one(&mut **tx)
+        .await
+}
+
+impl SqliteQuoteStore {
+    pub async fn connect(url: &str) -> Result<Self, MigrateError> {
+        let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+        // An in-memory database only lives as long as its connection.
+        let max_connections = if url.contains(":memory:") { 1 } else { 10 };
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await?;
+        sqlx::migrate!("./migrations/sqlite").run(&pool).await?;
+        file_authors(&pool).await.map_err(MigrateError::Execute)?;
+        Ok(SqliteQuoteStore {
+            pool,
+            events: EventBus::default(),
+        })
+    }
+}
+
+#[async_trait]
+impl QuoteStore for SqliteQuoteStore {
+    async fn insert(
+        &self,
+        id: Uuid,
+        author: &str,
+        quote: &str,
+        actor: Option<&str>,
+    ) -> StoreResult<Quote> {
+        let mut tx = self.pool.begin().await?;
+        let author_id = author_id(&mut tx, author).await?;
+        let result = sqlx::query_as(
+            "INSERT INTO quotes (id, author, author_id, quote, created_at) VALUES ($1, $2, $3, $4, $5) RETURNING *",
+        )
+        .bind(id)
+        .bind(author)
+        .bind(author_id)
+        .bind(quote)
+        .bind(Utc::now())
+        .fetch_one(&mut *tx)
+        .await?;
+        record_version(&mut tx, &result).await?;
+        let record = AuditRecord::quote("create", actor, None, Some(&result));
+        append_audit(&mut tx, &record).await?;
+        tx.commit().await?;
+        self.events.publish(QuoteEventKind::Created, &result);
+        Ok(result)
+    }
+
+    async fn get(&self, id: Uuid) -> StoreResult<Option<Quote>> {
+        sqlx::query_as("SELECT * FROM quotes WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn update(
+        &self,
+        id: Uuid,
+        author: &str,
+        quote: &str,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut tx = self.pool.begin().await?;
+        let result = update(
+            &mut tx,
+            "update",
+            id,
+            author,
+            quote,
+            expected_version,
+            actor,
+        )
+        .await?;
+        tx.commit().await?;
+        if let Some(result) = &result {
+            self.events.publish(QuoteEventKind::Updated, result);
+        }
+        Ok(result)
+    }
+
+    async fn remove(
+        &self,
+        id: Uuid,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut tx = self.pool.begin().await?;
+        let result: Option<Quote> = sqlx::query_as(
+            "UPDATE quotes SET deleted_at = $1 WHERE id = $2 AND deleted_at IS NULL AND ($3 IS NULL OR version = $3) RETURNING *",
+        )
+        .bind(Utc::now())
+        .bind(id)
+        .bind(expected_version)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(result) = &result {
+            let record = AuditRecord::quote("delete", actor, Some(result), None);
+            append_audit(&mut tx, &record).await?;
+        }
+        tx.commit().await?;
+        if let Some(result) = &result {
+            self.events.publish(QuoteEventKind::Deleted, result);
+        }
+        Ok(result)
+    }
+
+    async fn get_deleted(&self, id: Uuid) -> StoreResult<Option<Quote>> {
+        sqlx::query_as("SELECT * FROM quotes WHERE id = $1 AND deleted_at IS NOT NULL")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn restore(
+        &self,
+        id: Uuid,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut tx = self.pool.begin().await?;
+        let result: Option<Quote> = sqlx::query_as(
+            "UPDATE quotes SET deleted_at = NULL WHERE id = $1 AND deleted_at IS NOT NULL AND ($2 IS NULL OR version = $2) RETURNING *",
+        )
+        .bind(id)
+        .bind(expected_version)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(result) = &result {
+            let record = AuditRecord::quote("restore", actor, None, Some(result));
+            append_audit(&mut tx, &record).await?;
+        }
+        tx.commit().await?;
+        if let Some(result) = &result {
+            self.events.publish(QuoteEventKind::Created, result);
+        }
+        Ok(result)
+    }
+
+    async fn purge(
+        &self,
+        id: Uuid,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut tx = self.pool.begin().await?;
+        let result: Option<Quote> = sqlx::query_as(
+            "DELETE FROM quotes WHERE id = $1 AND deleted_at IS NOT NULL AND ($2 IS NULL OR version = $2) RETURNING *",
+        )
+        .bind(id)
+        .bind(expected_version)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(result) = &result {
+            let record = AuditRecord::quote("purge", actor, Some(result), None);
+            append_audit(&mut tx, &record).await?;
+        }
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> StoreResult<u64> {
+        let mut tx = self.pool.begin().await?;
+        let purged: Vec<Quote> =
+            sqlx::query_as("DELETE FROM quotes WHERE deleted_at < $1 RETURNING *")
+                .bind(cutoff)
+                .fetch_all(&mut *tx)
+                .await?;
+        for quote in &purged {
+            let record = AuditRecord::quote("purge", Some(PURGE_ACTOR), Some(quote), None);
+            append_audit(&mut tx, &record).await?;
+        }
+        tx.commit().await?;
+        Ok(purged.len() as u64)
+    }
+
+    async fn list(&self, offset: i64, limit: i64, tags: &TagFilter) -> StoreResult<Vec<Quote>> {
+        sqlx::query_as(&format!(
+            "SELECT * FROM quotes WHERE deleted_at IS NULL AND {TAG_FILTER} ORDER BY created_at, id LIMIT ? OFFSET ?"
+        ))
+        .bind(tags_json(tags))
+        .bind(tags.min_matches())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn list_after(
+        &self,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> StoreResult<Vec<Quote>> {
+        let (created_at, id) = after.unzip();
+        sqlx::query_as(
+            "SELECT * FROM quotes WHERE deleted_at IS NULL AND ($1 IS NULL OR (created_at, id) > ($1, $2)) ORDER BY created_at, id LIMIT $3",
+        )
+        .bind(created_at)
+        .bind(id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count(&self) -> StoreResult<i64> {
+        sqlx::query_scalar("SELECT count(*) FROM quotes WHERE deleted_at IS NULL")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    async fn search(&self, query: &SearchQuery) -> StoreResult<Vec<SearchHit>> {
+        // No full-text index here, so narrow down by author and rank in Rust.
+        let quotes: Vec<Quote> = sqlx::query_as(&format!(
+            "SELECT * FROM quotes WHERE deleted_at IS NULL AND (? IS NULL OR lower(author) = lower(?)) AND {TAG_FILTER}"
+        ))
+        .bind(&query.author)
+        .bind(&query.author)
+        .bind(tags_json(&query.tags))
+        .bind(query.tags.min_matches())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(naive_search(quotes, query))
+    }
+
+    async fn authors(&self) -> StoreResult<Vec<AuthorStats>> {
+        sqlx::query_as(
+            "SELECT authors.id, authors.name, count(*) AS quote_count, min(quotes.created_at) AS first_quote_at, max(quotes.created_at) AS last_quote_at FROM authors JOIN quotes ON quotes.author_id = authors.id AND quotes.deleted_at IS NULL GROUP BY authors.id ORDER BY authors.normalized_name",
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn quotes_by_author(&self, author_id: Uuid) -> StoreResult<Option<Vec<Quote>>> {
+        let known: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM authors WHERE id = $1)")
+            .bind(author_id)
+            .fetch_one(&self.pool)
+            .await?;
+        if !known {
+            return Ok(None);
+        }
+        sqlx::query_as(
+            "SELECT * FROM quotes WHERE author_id = $1 AND deleted_at IS NULL ORDER BY created_at, id",
+        )
+        .bind(author_id)
+        .fetch_all(&self.pool)
+        .await
+        .map(Some)
+    }
+
+    async fn tag(&self, id: Uuid, tag: &str) -> StoreResult<Option<Vec<String>>> {
+        let mut tx = self.pool.begin().await?;
+        if !is_live(&mut tx, id).await? {
+            return Ok(None);
+        }
+        sqlx::query("INSERT INTO tags (id, name) VALUES ($1, $2) ON CONFLICT (name) DO NOTHING")
+            .bind(Uuid::new_v4())
+            .bind(tag)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO quote_tags (quote_id, tag_id) SELECT $1, id FROM tags WHERE name = $2 ON CONFLICT DO NOTHING",
+        )
+        .bind(id)
+        .bind(tag)
+        .execute(&mut *tx)
+        .await?;
+        let tags = quote_tags(&mut tx, id).await?;
+        tx.commit().await?;
+        Ok(Some(tags))
+    }
+
+    async fn untag(&self, id: Uuid, tag: &str) -> StoreResult<Option<Vec<String>>> {
+        let mut tx = self.pool.begin().await?;
+        if !is_live(&mut tx, id).await? {
+            return Ok(None);
+        }
+        sqlx::query(
+            "DELETE FROM quote_tags WHERE quote_id = $1 AND tag_id IN (SELECT id FROM tags WHERE name = $2)",
+        )
+        .bind(id)
+        .bind(tag)
+        .execute(&mut *tx)
+        .await?;
+        let tags = quote_tags(&mut tx, id).await?;
+        tx.commit().await?;
+        Ok(Some(tags))
+    }
+
+    async fn tags(&self) -> StoreResult<Vec<TagCount>> {
+        sqlx::query_as(
+            "SELECT tags.name, count(*) AS quote_count FROM tags JOIN quote_tags ON quote_tags.tag_id = tags.id JOIN quotes ON quotes.id = quote_tags.quote_id AND quotes.deleted_at IS NULL GROUP BY tags.name ORDER BY tags.name",
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn vote(&self, id: Uuid, voter: &str, vote: Option<Vote>) -> StoreResult<Option<Rating>> {
+        let mut tx = self.pool.begin().await?;
+        if !is_live(&mut tx, id).await? {
+            return Ok(None);
+        }
+        match vote {
+            Some(vote) => sqlx::query(
+                "INSERT INTO quote_votes (quote_id, voter, value, voted_at) VALUES ($1, $2, $3, $4) ON CONFLICT (quote_id, voter) DO UPDATE SET value = excluded.value, voted_at = excluded.voted_at",
+            )
+            .bind(id)
+            .bind(voter)
+            .bind(vote.value())
+            .bind(Utc::now()),
+            None => sqlx::query("DELETE FROM quote_votes WHERE quote_id = $1 AND voter = $2")
+                .bind(id)
+                .bind(voter),
+        }
+        .execute(&mut *tx)
+        .await?;
+        let row: Option<RatingRow> =
+            sqlx::query_as(&format!("SELECT * FROM ({RATINGS}) WHERE quote_id = ?"))
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        tx.commit().await?;
+        Ok(Some(row.as_ref().map(Rating::from).unwrap_or_default()))
+    }
+
+    async fn ratings(&self, ids: &[Uuid]) -> StoreResult<HashMap<Uuid, Rating>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        // Ids are stored as blobs, which json_each can't produce.
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let sql = format!("SELECT * FROM ({RATINGS}) WHERE quote_id IN ({placeholders})");
+        let rows: Vec<RatingRow> = ids
+            .iter()
+            .fold(sqlx::query_as(&sql), |query, id| query.bind(id))
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| (row.quote_id, Rating::from(row)))
+            .collect())
+    }
+
+    async fn top(&self, offset: i64, limit: i64) -> StoreResult<Vec<RatedQuote>> {
+        // No sqrt in every SQLite build, so rank in Rust.
+        let quotes: Vec<Quote> = sqlx::query_as(
+            "SELECT * FROM quotes WHERE deleted_at IS NULL AND id IN (SELECT quote_id FROM quote_votes)",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let ratings = self
+            .ratings(&quotes.iter().map(|quote| quote.id).collect::<Vec<_>>())
+            .await?;
+        let rated = quotes
+            .into_iter()
+            .filter_map(|quote| {
+                Some(RatedQuote {
+                    rating: *ratings.get(&quote.id)?,
+                    quote,
+                })
+            })
+            .collect();
+        Ok(rank_top(rated, offset, limit))
+    }
+
+    async fn events_since(&self, after: i64) -> StoreResult<Vec<QuoteEvent>> {
+        Ok(self.events.since(after))
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<QuoteEvent> {
+        self.events.subscribe()
+    }
+
+    async fn append_audit(&self, record: &AuditRecord) -> StoreResult<AuditEntry> {
+        let mut tx = self.pool.begin().await?;
+        let entry = append_audit(&mut tx, record).await?;
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    async fn audit(&self, query: &AuditQuery) -> StoreResult<Vec<AuditEntry>> {
+        let rows: Vec<AuditRow> = sqlx::query_as(
+            "SELECT * FROM quote_audit WHERE ($1 IS NULL OR quote_id = $1) AND ($2 IS NULL OR created_at >= $2) AND ($3 IS NULL OR created_at < $3) ORDER BY id DESC LIMIT $4 OFFSET $5",
+        )
+        .bind(query.quote_id)
+        .bind(query.since)
+        .bind(query.until)
+        .bind(query.limit)
+        .bind(query.offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(AuditEntry::from).collect())
+    }
+
+    async fn import(&self, quotes: &[Quote], actor: Option<&str>) -> StoreResult<Vec<Uuid>> {
+        let mut tx = self.pool.begin().await?;
+        let mut taken = Vec::new();
+        for quote in quotes {
+            let author_id = author_id(&mut tx, &quote.author).await?;
+            let inserted = sqlx::query(
+                "INSERT INTO quotes (id, author, author_id, quote, created_at, version) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (id) DO NOTHING",
+            )
+            .bind(quote.id)
+            .bind(&quote.author)
+            .bind(author_id)
+            .bind(&quote.quote)
+            .bind(quote.created_at)
+            .bind(quote.version)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if inserted == 0 {
+                taken.push(quote.id);
+            } else {
+                record_version(&mut tx, quote).await?;
+            }
+        }
+        // Dropping the transaction rolls it back.
+        if taken.is_empty() {
+            for quote in quotes {
+                let record = AuditRecord::quote("import", actor, None, Some(quote));
+                append_audit(&mut tx, &record).await?;
+            }
+            tx.commit().await?;
+            for quote in quotes {
+                self.events.publish(QuoteEventKind::Created, quote);
+            }
+        }
+        Ok(taken)
+    }
+
+    async fn reset(&self, actor: Option<&str>) -> StoreResult<()> {
+        let mut tx = self.pool.begin().await?;
+        // Versions go with their quotes through the foreign key cascade.
+        sqlx::query("DELETE FROM quotes").execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM authors").execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM tags").execute(&mut *tx).await?;
+        append_audit(&mut tx, &AuditRecord::book("reset", actor)).await?;
+        tx.commit().await?;
+        self.events.clear();
+        Ok(())
+    }
+
+    async fn history(&self, id: Uuid) -> StoreResult<Option<Vec<QuoteVersion>>> {
+        let versions: Vec<QuoteVersion> =
+            sqlx::query_as("SELECT quote_versions.* FROM quote_versions JOIN quotes ON quotes.id = quote_versions.quote_id AND quotes.deleted_at IS NULL WHERE quote_id = $1 ORDER BY version")
+                .bind(id)
+                .fetch_all(&self.pool)
+                .await?;
+        // Every quote has at least its first version.
+        Ok(Some(versions).filter(|versions| !versions.is_empty()))
+    }
+
+    async fn version(&self, id: Uuid, version: i32) -> StoreResult<Option<QuoteVersion>> {
+        sqlx::query_as("SELECT quote_versions.* FROM quote_versions JOIN quotes ON quotes.id = quote_versions.quote_id AND quotes.deleted_at IS NULL WHERE quote_id = $1 AND quote_versions.version = $2")
+            .bind(id)
+            .bind(version)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn revert(
+        &self,
+        id: Uuid,
+        version: i32,
+        expected_version: Option<i32>,
+        actor: Option<&str>,
+    ) -> StoreResult<Option<Quote>> {
+        let mut tx = self.pool.begin().await?;
+        let old: Option<QuoteVersion> =
+            sqlx::query_as("SELECT * FROM quote_versions WHERE quote_id = $1 AND version = $2")
+                .bind(id)
+                .bind(version)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let Some(old) = old else {
+            return Ok(None);
+        };
+        let result = update(
+            &mut tx,
+            "revert",
+            id,
+            &old.author,
+            &old.quote,
+            expected_version,
+            actor,
+        )
+        .await?;
+        tx.commit().await?;
+        if let Some(result) = &result {
+            self.events.publish(QuoteEventKind::Updated, result);
+        }
+        Ok(result)
+    }
+}
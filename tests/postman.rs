@@ -0,0 +1,216 @@
+//! Replays `shuttlings-cch24.postman_collection.json` against the in-process
+//! router and snapshots every response.
+//!
+//! Day 19 runs against the quote store named by `TEST_DATABASE_URL`, falling
+//! back to the in-memory store when it isn't set. The store is reset first,
+//! which is why the regular `DATABASE_URL` is never used. Refresh the snapshots with
+//! `INSTA_UPDATE=always cargo test --test postman`.
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Request},
+    Router,
+};
+use serde_json::Value;
+use shuttlings_cch24::{
+    build_router,
+    store::{self, QuoteStore},
+    AppState,
+};
+use tower::ServiceExt;
+
+const COLLECTION: &str = include_str!("../shuttlings-cch24.postman_collection.json");
+
+struct Case {
+    name: String,
+    method: String,
+    uri: String,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+}
+
+fn load_cases() -> Vec<(String, Vec<Case>)> {
+    let collection: Value = serde_json::from_str(COLLECTION).unwrap();
+    collection["item"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|folder| {
+            let folder_name = folder["name"].as_str().unwrap().to_string();
+            let cases = folder["item"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .enumerate()
+                .map(|(i, item)| parse_case(&folder_name, i, item))
+                .collect();
+            (folder_name, cases)
+        })
+        .collect()
+}
+
+fn parse_case(folder: &str, index: usize, item: &Value) -> Case {
+    let request = &item["request"];
+    let url = &request["url"];
+    let path = url["path"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|segment| segment.as_str().unwrap())
+        .collect::<Vec<_>>()
+        .join("/");
+    let query = url["query"]
+        .as_array()
+        .map(|params| {
+            params
+                .iter()
+                .map(|p| {
+                    format!(
+                        "{}={}",
+                        p["key"].as_str().unwrap(),
+                        p["value"].as_str().unwrap()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("&")
+        })
+        .filter(|query| !query.is_empty());
+    let uri = match query {
+        Some(query) => format!("/{path}?{query}"),
+        None => format!("/{path}"),
+    };
+
+    let headers = request["header"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|h| {
+            (
+                h["key"].as_str().unwrap().to_string(),
+                h["value"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+
+    Case {
+        name: format!("{}_{:02}", folder.to_lowercase(), index + 1),
+        method: request["method"].as_str().unwrap().to_string(),
+        uri,
+        headers,
+        body: request["body"]["raw"].as_str().map(str::to_string),
+    }
+}
+
+/// Cookies set by earlier responses, used to fill in placeholders such as
+/// `gift=(JWT)` the way a Postman user would by hand.
+#[derive(Default)]
+struct CookieJar(HashMap<String, String>);
+
+impl CookieJar {
+    fn store(&mut self, set_cookie: &str) {
+        let pair = set_cookie.split(';').next().unwrap_or_default();
+        if let Some((name, value)) = pair.split_once('=') {
+            self.0.insert(name.trim().to_string(), value.to_string());
+        }
+    }
+
+    fn fill(&self, cookie: &str) -> String {
+        cookie
+            .split("; ")
+            .map(|pair| match pair.split_once('=') {
+                Some((name, value)) if value.starts_with('(') => {
+                    let value = self.0.get(name).map(String::as_str).unwrap_or(value);
+                    format!("{name}={value}")
+                }
+                _ => pair.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Replaces values that change between runs so snapshots stay stable.
+fn redact(body: &str) -> String {
+    let Ok(mut json) = serde_json::from_str::<Value>(body) else {
+        return body.to_string();
+    };
+    if let Some(object) = json.as_object_mut() {
+        for key in ["id", "created_at"] {
+            if object.contains_key(key) {
+                object.insert(key.to_string(), Value::String(format!("[{key}]")));
+            }
+        }
+    }
+    serde_json::to_string_pretty(&json).unwrap()
+}
+
+async fn replay(router: &Router, jar: &mut CookieJar, case: &Case) -> String {
+    let mut request = Request::builder()
+        .method(case.method.as_str())
+        .uri(&case.uri);
+    for (key, value) in &case.headers {
+        let value = if key.eq_ignore_ascii_case("cookie") {
+            jar.fill(value)
+        } else {
+            value.clone()
+        };
+        request = request.header(key, value);
+    }
+    let request = request
+        .body(
+            case.body
+                .clone()
+                .map(Body::from)
+                .unwrap_or_else(Body::empty),
+        )
+        .unwrap();
+
+    let response = router.clone().oneshot(request).await.unwrap();
+
+    let mut snapshot = format!(
+        "{} {}\nstatus: {}\n",
+        case.method,
+        case.uri,
+        response.status()
+    );
+    for name in [header::CONTENT_TYPE, header::SET_COOKIE] {
+        for value in response.headers().get_all(&name) {
+            let value = value.to_str().unwrap();
+            if name == header::SET_COOKIE {
+                jar.store(value);
+            }
+            snapshot.push_str(&format!("{name}: {value}\n"));
+        }
+    }
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    snapshot.push('\n');
+    snapshot.push_str(&redact(&String::from_utf8_lossy(&body)));
+    snapshot
+}
+
+async fn quote_store() -> Arc<dyn QuoteStore> {
+    let url = std::env::var("TEST_DATABASE_URL").unwrap_or_else(|_| "memory".to_string());
+    let quotes = store::connect(&url).await.unwrap();
+    quotes.reset(None).await.unwrap();
+    quotes
+}
+
+#[tokio::test]
+async fn postman_collection() {
+    let router = build_router(AppState::new(quote_store().await));
+    let mut jar = CookieJar::default();
+
+    for (_, cases) in load_cases() {
+        for case in cases {
+            let snapshot = replay(&router, &mut jar, &case).await;
+            insta::with_settings!({
+                snapshot_path => "snapshots/postman",
+                prepend_module_to_snapshot => false,
+            }, {
+                insta::assert_snapshot!(case.name.as_str(), snapshot);
+            });
+        }
+    }
+}
@@ -0,0 +1,1156 @@
+use std::sync::Arc;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Request, StatusCode},
+    Router,
+};
+use futures::StreamExt;
+use shuttlings_cch24::{
+    auth::AdminAuth, build_router, day::day12::Games, store::MemoryQuoteStore, AppState,
+};
+use tower::ServiceExt;
+
+fn router() -> Router {
+    build_router(AppState::new(Arc::new(MemoryQuoteStore::default())))
+}
+
+async fn send(router: Router, request: Request<Body>) -> (StatusCode, String) {
+    let response = router.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn get(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+/// Creates a game playing by the JSON `rules`, or the default rules when
+/// empty, and returns its id.
+async fn new_game(router: &Router, rules: &str) -> String {
+    let request = Request::post("/12/games")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(rules.to_string()))
+        .unwrap();
+    let (status, body) = send(router.clone(), request).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let game: serde_json::Value = serde_json::from_str(&body).unwrap();
+    game["id"].as_str().unwrap().to_string()
+}
+
+/// Drafts a quote and returns it as the API shows it.
+async fn draft(router: &Router, author: &str, quote: &str) -> serde_json::Value {
+    let request = Request::post("/19/draft")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            serde_json::json!({ "author": author, "quote": quote }).to_string(),
+        ))
+        .unwrap();
+    let (status, body) = send(router.clone(), request).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    serde_json::from_str(&body).unwrap()
+}
+
+#[tokio::test]
+async fn hello_world() {
+    let (status, body) = send(router(), get("/")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "Hello, bird!");
+}
+
+#[tokio::test]
+async fn ipv4_encryption() {
+    let (status, body) = send(router(), get("/2/dest?from=10.0.0.0&key=1.2.3.255")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "11.2.3.255");
+}
+
+#[tokio::test]
+async fn missing_query_param_is_problem_details() {
+    let response = router()
+        .oneshot(get("/2/dest?from=10.0.0.0"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "application/problem+json"
+    );
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let problem: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(problem["status"], 400);
+    assert_eq!(problem["detail"], "key is not set.");
+}
+
+#[tokio::test]
+async fn place_rejects_unknown_team() {
+    let request = Request::post("/12/place/tea/1")
+        .body(Body::empty())
+        .unwrap();
+    let (status, _) = send(router(), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn games_are_played_independently() {
+    let router = router();
+    let ids = [new_game(&router, "").await, new_game(&router, "").await];
+
+    let request = Request::post(format!("/12/games/{}/place/cookie/2", ids[0]))
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = send(router.clone(), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains('🍪'));
+    let (_, body) = send(router.clone(), get(&format!("/12/games/{}/board", ids[1]))).await;
+    assert!(!body.contains('🍪'));
+    let (_, body) = send(router.clone(), get("/12/board")).await;
+    assert!(!body.contains('🍪'));
+
+    let request = Request::post(format!("/12/games/{}/reset", ids[0]))
+        .body(Body::empty())
+        .unwrap();
+    send(router.clone(), request).await;
+    let (_, body) = send(router.clone(), get(&format!("/12/games/{}/board", ids[0]))).await;
+    assert!(!body.contains('🍪'));
+
+    let (status, _) = send(
+        router.clone(),
+        get(&format!("/12/games/{}/board", uuid::Uuid::new_v4())),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(router, get("/12/games/nope/board")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn games_take_board_size_and_win_length() {
+    let router = router();
+    let create = |body: &str| {
+        Request::post("/12/games")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let (status, body) = send(router.clone(), create(r#"{"connect":5}"#)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body.contains("must fit on the board"));
+    let (status, _) = send(router.clone(), create(r#"{"width":0}"#)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, body) = send(router.clone(), create(r#"{"width":7,"height":6}"#)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let game: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(game["connect"], 4);
+    let id = game["id"].as_str().unwrap().to_string();
+    let place = |team: &str, column: usize| {
+        Request::post(format!("/12/games/{id}/place/{team}/{column}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let (status, _) = send(router.clone(), place("cookie", 8)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    // Cookie climbs a diagonal that doesn't touch a corner.
+    let moves = [
+        ("cookie", 2),
+        ("milk", 3),
+        ("cookie", 3),
+        ("milk", 4),
+        ("milk", 4),
+        ("cookie", 4),
+        ("milk", 5),
+        ("milk", 5),
+        ("milk", 5),
+    ];
+    for (team, column) in moves {
+        let (status, body) = send(router.clone(), place(team, column)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!body.contains("wins"));
+    }
+    let (_, body) = send(router.clone(), place("cookie", 5)).await;
+    let rows: Vec<_> = body.lines().collect();
+    assert_eq!(rows.len(), 8);
+    assert_eq!(rows[6], "⬜".repeat(9));
+    assert_eq!(rows[7], "🍪 wins!");
+    let (status, _) = send(router, place("milk", 7)).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn strict_games_enforce_turns() {
+    let router = router();
+    let id = new_game(&router, r#"{"strict":true,"first":"milk"}"#).await;
+    let place = |team: &str| {
+        Request::post(format!("/12/games/{id}/place/{team}/1"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let (_, body) = send(router.clone(), get(&format!("/12/games/{id}/board"))).await;
+    assert!(body.ends_with("🥛 to move.\n"));
+    let (status, body) = send(router.clone(), place("cookie")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let problem: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(problem["detail"], "It is milk's turn, not cookie's");
+
+    let (status, body) = send(router.clone(), place("milk")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.ends_with("🍪 to move.\n"));
+    let (status, _) = send(router.clone(), place("milk")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send(router, place("cookie")).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn game_history_undo_and_replay() {
+    let router = router();
+    let id = new_game(&router, "").await;
+    let post = |path: &str, body: &str| {
+        Request::post(format!("/12/games/{id}/{path}"))
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    for path in ["place/cookie/1", "place/milk/2", "place/cookie/1"] {
+        let (status, _) = send(router.clone(), post(path, "")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let (_, body) = send(router.clone(), get(&format!("/12/games/{id}/history"))).await;
+    let history: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(history["notation"], "c1m2c1");
+    assert_eq!(history["moves"][2]["team"], "cookie");
+    assert_eq!(history["moves"][2]["column"], 1);
+    assert_eq!(history["moves"][2]["row"], 2);
+
+    let (_, body) = send(router.clone(), get(&format!("/12/games/{id}/replay/1"))).await;
+    assert_eq!(
+        body,
+        "⬜⬛⬛⬛⬛⬜\n⬜⬛⬛⬛⬛⬜\n⬜⬛⬛⬛⬛⬜\n⬜🍪⬛⬛⬛⬜\n⬜⬜⬜⬜⬜⬜\n"
+    );
+    let (status, _) = send(router.clone(), get(&format!("/12/games/{id}/replay/4"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = send(router.clone(), post("undo", "")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.starts_with("⬜⬛⬛⬛⬛⬜\n⬜⬛⬛⬛⬛⬜\n⬜⬛⬛⬛⬛⬜\n⬜🍪🥛⬛⬛⬜\n"));
+    let (_, body) = send(router.clone(), get(&format!("/12/games/{id}/export"))).await;
+    assert_eq!(body, "c1m2");
+
+    let (status, body) = send(router.clone(), post("import", "c1c1c1c1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.ends_with("🍪 wins!\n"));
+    let (status, body) = send(router.clone(), post("import", "c1c2c3c4m1")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let problem: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        problem["invalid_fields"][0]["message"],
+        "move 5: the game is over"
+    );
+    // Empty imports would reset the game past the admin guard.
+    let (status, _) = send(router.clone(), post("import", " \n")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let request = Request::post("/12/import").body(Body::empty()).unwrap();
+    let (status, _) = send(router.clone(), request).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    // A rejected import leaves the game alone.
+    let (_, body) = send(router.clone(), get(&format!("/12/games/{id}/export"))).await;
+    assert_eq!(body, "c1c1c1c1");
+
+    for _ in 0..4 {
+        send(router.clone(), post("undo", "")).await;
+    }
+    let (status, _) = send(router, post("undo", "")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn ai_plays_and_answers_moves() {
+    let router = router();
+    let post = |uri: String| Request::post(uri).body(Body::empty()).unwrap();
+
+    let id = new_game(&router, r#"{"width":7,"height":6}"#).await;
+    for _ in 0..3 {
+        send(
+            router.clone(),
+            post(format!("/12/games/{id}/place/cookie/1")),
+        )
+        .await;
+    }
+    // Milk blocks the open column, then cookie finds another way.
+    let (status, _) = send(router.clone(), post(format!("/12/games/{id}/ai-move/milk"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send(router.clone(), get(&format!("/12/games/{id}/export"))).await;
+    assert_eq!(body, "c1c1c1m1");
+    let (status, _) = send(
+        router.clone(),
+        post(format!("/12/games/{id}/ai-move/cookie?depth=11")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    for _ in 0..2 {
+        send(
+            router.clone(),
+            post(format!("/12/games/{id}/place/cookie/2")),
+        )
+        .await;
+    }
+    send(
+        router.clone(),
+        post(format!("/12/games/{id}/place/cookie/3")),
+    )
+    .await;
+    let (_, body) = send(
+        router.clone(),
+        post(format!("/12/games/{id}/ai-move/cookie")),
+    )
+    .await;
+    assert!(body.ends_with("🍪 wins!\n"));
+    let (status, _) = send(router.clone(), post(format!("/12/games/{id}/ai-move/milk"))).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    let rules = r#"{"width":7,"height":6,"strict":true,"ai":"milk","ai_depth":2}"#;
+    let id = new_game(&router, rules).await;
+    let (status, _) = send(router.clone(), post(format!("/12/games/{id}/ai-move/milk"))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (_, body) = send(
+        router.clone(),
+        post(format!("/12/games/{id}/place/cookie/4")),
+    )
+    .await;
+    assert!(body.contains('🥛'));
+    assert!(body.ends_with("🍪 to move.\n"));
+    let (_, body) = send(router.clone(), get(&format!("/12/games/{id}/history"))).await;
+    let history: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(history["moves"].as_array().unwrap().len(), 2);
+    // Undoing takes back the AI's answer along with the player's move.
+    let (_, body) = send(router.clone(), post(format!("/12/games/{id}/undo"))).await;
+    assert!(!body.contains('🥛'));
+    assert!(body.ends_with("🍪 to move.\n"));
+    let (_, body) = send(router.clone(), get(&format!("/12/games/{id}/export"))).await;
+    assert_eq!(body, "");
+
+    // Moves the AI made without being answered can be taken back too.
+    let rules = r#"{"strict":true,"first":"milk","ai":"milk","ai_depth":1}"#;
+    let id = new_game(&router, rules).await;
+    let (status, _) = send(router.clone(), post(format!("/12/games/{id}/ai-move/milk"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send(router.clone(), post(format!("/12/games/{id}/undo"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.ends_with("🥛 to move.\n"));
+    let (_, body) = send(router, get(&format!("/12/games/{id}/export"))).await;
+    assert_eq!(body, "");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn games_stay_readable_while_the_ai_thinks() {
+    let router = router();
+    let id = new_game(&router, r#"{"width":7,"height":6}"#).await;
+
+    let request = Request::post(format!(
+        "/12/games/{id}/ai-move/cookie?depth=10&time_ms=2000"
+    ))
+    .body(Body::empty())
+    .unwrap();
+    let thinking = tokio::spawn(send(router.clone(), request));
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    let board = send(router, get(&format!("/12/games/{id}/board")));
+    let (status, _) = tokio::time::timeout(std::time::Duration::from_millis(500), board)
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = thinking.await.unwrap();
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn boards_are_negotiated_as_json() {
+    let router = router();
+    let id = new_game(
+        &router,
+        r#"{"width":3,"height":2,"connect":2,"strict":true}"#,
+    )
+    .await;
+    let place = |team: &str, column: usize| {
+        Request::post(format!("/12/games/{id}/place/{team}/{column}"))
+            .header(header::ACCEPT, "text/html, application/json;q=0.9")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let (_, body) = send(router.clone(), place("cookie", 1)).await;
+    let board: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        board,
+        serde_json::json!({
+            "grid": [[null, null, null], ["cookie", null, null]],
+            "winner": null,
+            "draw": false,
+            "next_turn": "milk",
+            "legal_columns": [1, 2, 3],
+        })
+    );
+    send(router.clone(), place("milk", 3)).await;
+    let (_, body) = send(router.clone(), place("cookie", 1)).await;
+    let board: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(board["winner"], "cookie");
+    assert_eq!(board["next_turn"], serde_json::Value::Null);
+    assert_eq!(board["legal_columns"], serde_json::json!([]));
+    let (status, body) = send(router.clone(), place("milk", 2)).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(body.starts_with('{'));
+
+    // Emoji stay the default.
+    let (_, body) = send(router.clone(), get(&format!("/12/games/{id}/board"))).await;
+    assert!(body.ends_with("🍪 wins!\n"));
+    let request = Request::get(format!("/12/games/{id}/board"))
+        .header(header::ACCEPT, "application/json")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+    // Quality values decide between JSON and emoji.
+    for (accept, json) in [
+        ("application/json;q=0", false),
+        ("text/plain, application/json;q=0.5", false),
+        ("text/*;q=0.8, application/json;q=0.5", false),
+        ("text/plain;q=0.5, application/json", true),
+        ("*/*, application/json", true),
+        ("*/*", false),
+    ] {
+        let request = Request::get(format!("/12/games/{id}/board"))
+            .header(header::ACCEPT, accept)
+            .body(Body::empty())
+            .unwrap();
+        let (_, body) = send(router.clone(), request).await;
+        assert_eq!(body.starts_with('{'), json, "{accept}");
+    }
+}
+
+#[tokio::test]
+async fn least_recently_used_games_make_room() {
+    let state = AppState {
+        games: Games::default().with_capacity(2),
+        ..AppState::new(Arc::new(MemoryQuoteStore::default()))
+    };
+    let router = build_router(state);
+    let mut boards = Vec::new();
+    for _ in 0..2 {
+        let id = new_game(&router, "").await;
+        boards.push(format!("/12/games/{id}/board"));
+    }
+    // Using the first game leaves the second as the one to drop.
+    let (status, _) = send(router.clone(), get(&boards[0])).await;
+    assert_eq!(status, StatusCode::OK);
+    new_game(&router, "").await;
+
+    let (status, _) = send(router.clone(), get(&boards[0])).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(router, get(&boards[1])).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn idle_games_are_evicted() {
+    let state = AppState {
+        games: Games::new(std::time::Duration::ZERO),
+        ..AppState::new(Arc::new(MemoryQuoteStore::default()))
+    };
+    let router = build_router(state);
+    let uri = format!("/12/games/{}/board", new_game(&router, "").await);
+
+    let (status, _) = send(router.clone(), get(&uri)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    // The default game never expires.
+    let (status, _) = send(router, get("/12/board")).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn quote_list_pagination() {
+    let router = router();
+    for i in 0..4 {
+        draft(&router, "Santa", &i.to_string()).await;
+    }
+
+    let (status, body) = send(router.clone(), get("/19/list")).await;
+    assert_eq!(status, StatusCode::OK);
+    let first: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(first["page"], 1);
+    assert_eq!(first["quotes"].as_array().unwrap().len(), 3);
+    let token = first["next_token"].as_str().unwrap();
+    assert_eq!(token.len(), 16);
+
+    let uri = format!("/19/list?token={token}");
+    let (status, body) = send(router.clone(), get(&uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    let second: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(second["page"], 2);
+    assert_eq!(second["quotes"].as_array().unwrap().len(), 1);
+    assert!(second["next_token"].is_null());
+
+    // Tokens are single use.
+    let (status, _) = send(router.clone(), get(&uri)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(router, get("/19/list?token=nope")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn quote_search() {
+    let router = router();
+    for (author, quote) in [("Santa", "Ho ho ho!"), ("Grinch", "Bah, ho hum")] {
+        draft(&router, author, quote).await;
+    }
+
+    let (status, body) = send(router.clone(), get("/19/search?q=ho&author=santa")).await;
+    assert_eq!(status, StatusCode::OK);
+    let found: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let results = found["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["author"], "Santa");
+    assert_eq!(
+        results[0]["snippet"],
+        "<mark>Ho</mark> <mark>ho</mark> <mark>ho</mark>!"
+    );
+    assert!(found["next_page"].is_null());
+
+    let (status, _) = send(router.clone(), get("/19/search")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(router, get("/19/search?q=ho&page=0")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn quote_export_import_round_trip() {
+    let source = router();
+    for quote in ["Ho ho ho!", "Ho, \"ho\"\nho"] {
+        draft(&source, "Santa", quote).await;
+    }
+
+    for format in ["jsonl", "csv"] {
+        let (status, exported) =
+            send(source.clone(), get(&format!("/19/export?format={format}"))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let target = router();
+        let import = |body: String| {
+            Request::post(format!("/19/import?format={format}"))
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let (status, body) = send(target.clone(), import(exported.clone())).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["imported"], 2);
+        let (_, reexported) =
+            send(target.clone(), get(&format!("/19/export?format={format}"))).await;
+        assert_eq!(reexported, exported);
+
+        // Importing again conflicts on every row and writes nothing.
+        let (status, body) = send(target, import(exported)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["errors"].as_array().unwrap().len(), 2);
+    }
+
+    let request = Request::post("/19/import")
+        .body(Body::from("{}\n\nnot json\n"))
+        .unwrap();
+    let (status, body) = send(router(), request).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let lines: Vec<_> = report["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["line"].as_u64().unwrap())
+        .collect();
+    assert_eq!(lines, [1, 3]);
+}
+
+#[tokio::test]
+async fn quotes_by_author() {
+    let router = router();
+    for author in ["Santa", "santa", "Grinch"] {
+        draft(&router, author, "Hi").await;
+    }
+
+    let (status, body) = send(router.clone(), get("/19/authors")).await;
+    assert_eq!(status, StatusCode::OK);
+    let authors: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(authors[1]["name"], "Santa");
+    assert_eq!(authors[1]["quote_count"], 2);
+
+    let uri = format!("/19/authors/{}/quotes", authors[1]["id"].as_str().unwrap());
+    let (status, body) = send(router.clone(), get(&uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    let quotes: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(quotes.as_array().unwrap().len(), 2);
+
+    let uri = format!("/19/authors/{}/quotes", uuid::Uuid::new_v4());
+    let (status, _) = send(router, get(&uri)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn quote_tags_filter_listings() {
+    let router = router();
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        let quote = draft(&router, "Santa", &format!("Ho {i}")).await;
+        ids.push(quote["id"].as_str().unwrap().to_string());
+    }
+    let tag = |method: &str, id: &str, tag: &str| {
+        Request::builder()
+            .method(method)
+            .uri(format!("/19/tags/{id}/{tag}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    for id in &ids[..4] {
+        let (status, _) = send(router.clone(), tag("PUT", id, "Jolly")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (_, body) = send(router.clone(), tag("PUT", &ids[4], "grumpy")).await;
+    assert_eq!(body, format!(r#"{{"id":"{}","tags":["grumpy"]}}"#, ids[4]));
+    let (status, _) = send(router.clone(), tag("PUT", &ids[4], "not%20ok")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (_, body) = send(router.clone(), get("/19/tags")).await;
+    assert_eq!(
+        body,
+        r#"[{"name":"grumpy","quote_count":1},{"name":"jolly","quote_count":4}]"#
+    );
+
+    // The continuation token keeps the filter.
+    let (_, body) = send(router.clone(), get("/19/list?tags=jolly")).await;
+    let first: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(first["quotes"].as_array().unwrap().len(), 3);
+    let uri = format!("/19/list?token={}", first["next_token"].as_str().unwrap());
+    let (_, body) = send(router.clone(), get(&uri)).await;
+    let second: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(second["quotes"].as_array().unwrap().len(), 1);
+
+    let (_, body) = send(router.clone(), get("/19/list?tags=jolly,grumpy")).await;
+    let all: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(all["quotes"].as_array().unwrap().is_empty());
+
+    let (status, body) = send(
+        router.clone(),
+        get("/19/search?q=ho&tags=jolly,grumpy&match=any&page=1"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let found: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(found["results"].as_array().unwrap().len(), 5);
+
+    let (_, body) = send(router, tag("DELETE", &ids[4], "grumpy")).await;
+    assert_eq!(body, format!(r#"{{"id":"{}","tags":[]}}"#, ids[4]));
+}
+
+#[tokio::test]
+async fn random_and_daily_quotes() {
+    let router = router();
+    let (status, _) = send(router.clone(), get("/19/daily")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    for i in 0..10 {
+        draft(&router, "Santa", &format!("Ho {i}")).await;
+    }
+
+    let mut picked = std::collections::HashSet::new();
+    for _ in 0..20 {
+        let (status, body) = send(router.clone(), get("/19/random")).await;
+        assert_eq!(status, StatusCode::OK);
+        let quote: serde_json::Value = serde_json::from_str(&body).unwrap();
+        picked.insert(quote["quote"].as_str().unwrap().to_string());
+    }
+    assert!(picked.len() > 1);
+
+    let (status, daily) = send(router.clone(), get("/19/daily")).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, again) = send(router, get("/19/daily")).await;
+    assert_eq!(daily, again);
+}
+
+#[tokio::test]
+async fn quote_votes_rank_the_top_list() {
+    let router = router();
+    let mut ids = Vec::new();
+    for quote in ["Ho ho ho!", "Bah humbug"] {
+        let quote = draft(&router, "Santa", quote).await;
+        ids.push(quote["id"].as_str().unwrap().to_string());
+    }
+    let vote = |id: &str, voter: &str, vote: &str| {
+        Request::put(format!("/19/votes/{id}/{voter}"))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(format!(r#"{{"vote":"{vote}"}}"#)))
+            .unwrap()
+    };
+    for voter in ["elf", "reindeer", "grinch"] {
+        send(router.clone(), vote(&ids[0], voter, "up")).await;
+    }
+    let (status, body) = send(router.clone(), vote(&ids[1], "grinch", "down")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, r#"{"upvotes":0,"downvotes":1,"score":0.0}"#);
+    let (status, _) = send(router.clone(), vote(&ids[1], "grinch", "sideways")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(router.clone(), vote(&ids[1], "%20grinch", "up")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send(
+        router.clone(),
+        vote(&uuid::Uuid::new_v4().to_string(), "grinch", "up"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let request = Request::delete(format!("/19/votes/{}/grinch", ids[0]))
+        .body(Body::empty())
+        .unwrap();
+    let (_, body) = send(router.clone(), request).await;
+    let rating: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(rating["upvotes"], 2);
+
+    let (status, body) = send(router.clone(), get("/19/top")).await;
+    assert_eq!(status, StatusCode::OK);
+    let top: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let quotes = top["quotes"].as_array().unwrap();
+    assert_eq!(quotes.len(), 2);
+    assert_eq!(quotes[0]["id"], ids[0].as_str());
+    assert!(quotes[0]["rating"]["score"].as_f64().unwrap() > 0.3);
+    assert!(top["next_page"].is_null());
+
+    let (_, body) = send(router, get(&format!("/19/cite/{}", ids[1]))).await;
+    let quote: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(quote["rating"]["downvotes"], 1);
+}
+
+/// Reads an event stream until `until` shows up in it.
+async fn read_events(body: Body, until: &str) -> String {
+    let mut frames = body.into_data_stream();
+    let mut text = String::new();
+    while !text.contains(until) {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), frames.next())
+            .await
+            .expect("event did not arrive")
+            .unwrap()
+            .unwrap();
+        text.push_str(std::str::from_utf8(&frame).unwrap());
+    }
+    text
+}
+
+#[tokio::test]
+async fn quote_events_stream_and_resume() {
+    let router = router();
+    let quote = draft(&router, "Santa", "Ho ho ho!").await;
+    let id = quote["id"].as_str().unwrap().to_string();
+    let update = Request::put(format!("/19/undo/{id}"))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(r#"{"author":"Santa","quote":"Ho!"}"#))
+        .unwrap();
+    send(router.clone(), update).await;
+
+    // Resuming after the first event replays the update, then goes live.
+    let request = Request::get("/19/events")
+        .header("last-event-id", "1")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/event-stream"
+    );
+    let mut body = response.into_body();
+    let replayed = read_events(body, "id: 2").await;
+    assert!(replayed.contains("event: updated"));
+    assert!(!replayed.contains("event: created"));
+
+    let request = Request::get("/19/events").body(Body::empty()).unwrap();
+    body = router.clone().oneshot(request).await.unwrap().into_body();
+    let remove = Request::delete(format!("/19/remove/{id}"))
+        .body(Body::empty())
+        .unwrap();
+    send(router.clone(), remove).await;
+    let live = read_events(body, "id: 3").await;
+    assert!(live.contains("event: deleted"));
+    assert!(live.contains(&id));
+    assert!(!live.contains("id: 2"));
+
+    let request = Request::get("/19/events")
+        .header("last-event-id", "nope")
+        .body(Body::empty())
+        .unwrap();
+    let (status, _) = send(router, request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn quote_mutations_are_audited() {
+    let router = router();
+    let request = Request::post("/19/draft")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header("x-actor", "santa")
+        .body(Body::from(r#"{"author":"Santa","quote":"Ho ho ho!"}"#))
+        .unwrap();
+    let (_, body) = send(router.clone(), request).await;
+    let quote: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let id = quote["id"].as_str().unwrap();
+
+    let request = Request::put(format!("/19/undo/{id}"))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(r#"{"author":"Santa","quote":"Ho!"}"#))
+        .unwrap();
+    send(router.clone(), request).await;
+    let request = Request::delete(format!("/19/remove/{id}"))
+        .body(Body::empty())
+        .unwrap();
+    send(router.clone(), request).await;
+
+    let (status, body) = send(router.clone(), get(&format!("/19/audit?quote_id={id}"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let log: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let entries = log["entries"].as_array().unwrap();
+    let actions: Vec<_> = entries
+        .iter()
+        .map(|e| e["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["delete", "update", "create"]);
+    assert_eq!(entries[1]["before"]["quote"], "Ho ho ho!");
+    assert_eq!(entries[1]["after"]["quote"], "Ho!");
+    assert_eq!(entries[2]["actor"], "santa");
+    assert!(entries[0]["actor"].is_null());
+
+    let (_, body) = send(router, get("/19/audit?since=2999-01-01T00:00:00Z")).await;
+    let log: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(log["entries"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn quote_payloads_are_validated() {
+    let draft = |body: String| {
+        Request::post("/19/draft")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let body = serde_json::json!({ "author": " Santa", "quote": "" }).to_string();
+    let (status, body) = send(router(), draft(body)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let problem: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        problem["invalid_fields"],
+        serde_json::json!([
+            { "field": "author", "message": "must not start or end with whitespace" },
+            { "field": "quote", "message": "must be 1 to 2000 characters" },
+        ])
+    );
+
+    let body = serde_json::json!({ "author": "Santa", "quote": "Ho\u{7}ho" }).to_string();
+    let (status, _) = send(router(), draft(body)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let body = serde_json::json!({ "author": "Santa", "quote": "ho ".repeat(10_000) }).to_string();
+    let (status, _) = send(router(), draft(body)).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn quote_history_versions_and_revert() {
+    let router = router();
+    let quote = draft(&router, "Santa", "Ho ho ho!").await;
+    let id = quote["id"].as_str().unwrap().to_string();
+    let request = Request::put(format!("/19/undo/{id}"))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(r#"{"author":"Santa","quote":"Ho!"}"#))
+        .unwrap();
+    send(router.clone(), request).await;
+
+    let (status, body) = send(router.clone(), get(&format!("/19/history/{id}"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let history: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let versions: Vec<_> = history
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| (v["version"].as_i64().unwrap(), v["quote"].as_str().unwrap()))
+        .collect();
+    assert_eq!(versions, [(1, "Ho ho ho!"), (2, "Ho!")]);
+
+    let (status, body) = send(router.clone(), get(&format!("/19/history/{id}/1"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let version: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(version["id"], id.as_str());
+    assert_eq!(version["quote"], "Ho ho ho!");
+    let (status, body) = send(router.clone(), get(&format!("/19/history/{id}/9"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let problem: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(problem["detail"], "Quote version not found");
+
+    let revert = |version: i32| {
+        Request::post(format!("/19/revert/{id}/{version}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let (status, body) = send(router.clone(), revert(1)).await;
+    assert_eq!(status, StatusCode::OK);
+    let reverted: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(reverted["quote"], "Ho ho ho!");
+    assert_eq!(reverted["version"], 3);
+    let (_, body) = send(router.clone(), get(&format!("/19/history/{id}"))).await;
+    let history: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(history.as_array().unwrap().len(), 3);
+    let (status, _) = send(router.clone(), revert(9)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Deleted quotes keep their history hidden until restored.
+    let request = Request::delete(format!("/19/remove/{id}"))
+        .body(Body::empty())
+        .unwrap();
+    send(router.clone(), request).await;
+    let (status, _) = send(router.clone(), get(&format!("/19/history/{id}"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(router.clone(), get(&format!("/19/history/{id}/1"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(router, revert(1)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn deleted_quotes_are_restored_or_purged() {
+    let router = router();
+    let quote = draft(&router, "Santa", "Ho ho ho!").await;
+    let id = quote["id"].as_str().unwrap().to_string();
+    let restore = |id: &str| {
+        Request::post(format!("/19/restore/{id}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let purge = |id: &str| {
+        Request::delete(format!("/19/purge/{id}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let remove = |id: &str| {
+        Request::delete(format!("/19/remove/{id}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // Live and unknown quotes are neither restored nor purged.
+    for request in [
+        restore(&id),
+        purge(&id),
+        restore(&uuid::Uuid::new_v4().to_string()),
+        purge(&uuid::Uuid::new_v4().to_string()),
+    ] {
+        let (status, body) = send(router.clone(), request).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let problem: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(problem["detail"], "Deleted quote not found");
+    }
+
+    send(router.clone(), remove(&id)).await;
+    let (status, body) = send(router.clone(), restore(&id)).await;
+    assert_eq!(status, StatusCode::OK);
+    let restored: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(restored["quote"], "Ho ho ho!");
+    let (status, _) = send(router.clone(), get(&format!("/19/cite/{id}"))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    send(router.clone(), remove(&id)).await;
+    let (status, body) = send(router.clone(), purge(&id)).await;
+    assert_eq!(status, StatusCode::OK);
+    let purged: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(purged["id"], id.as_str());
+    let (status, _) = send(router.clone(), restore(&id)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(router, get(&format!("/19/cite/{id}"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn quote_updates_honor_if_match() {
+    let router = router();
+    let request = Request::post("/19/draft")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(r#"{"author":"Santa","quote":"Ho ho ho!"}"#))
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let etag = response.headers()[header::ETAG]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let response = router
+        .clone()
+        .oneshot(get(&format!("/19/cite/{id}")))
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[header::ETAG], etag.as_str());
+
+    let undo = |if_match: &str| {
+        Request::put(format!("/19/undo/{id}"))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::IF_MATCH, if_match)
+            .body(Body::from(r#"{"author":"Santa","quote":"Ho!"}"#))
+            .unwrap()
+    };
+    let (status, _) = send(router.clone(), undo("\"stale\"")).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let (status, _) = send(router.clone(), undo(&etag)).await;
+    assert_eq!(status, StatusCode::OK);
+    // The first update bumped the version, so the old tag no longer matches.
+    let (status, _) = send(router.clone(), undo(&etag)).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+    let revert = |if_match: &str| {
+        Request::post(format!("/19/revert/{id}/1"))
+            .header(header::IF_MATCH, if_match)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let (status, _) = send(router.clone(), revert(&etag)).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let current = format!("\"{id}-2\"");
+    let response = router.clone().oneshot(revert(&current)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = format!("\"{id}-3\"");
+    assert_eq!(response.headers()[header::ETAG], etag.as_str());
+
+    let remove = Request::delete(format!("/19/remove/{id}"))
+        .header(header::IF_MATCH, "*")
+        .body(Body::empty())
+        .unwrap();
+    let (status, _) = send(router.clone(), remove).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Deleting keeps the version, so the last tag still guards the deleted quote.
+    let restore = |if_match: &str| {
+        Request::post(format!("/19/restore/{id}"))
+            .header(header::IF_MATCH, if_match)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let (status, _) = send(router.clone(), restore(&current)).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let response = router.clone().oneshot(restore(&etag)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::ETAG], etag.as_str());
+
+    let remove = Request::delete(format!("/19/remove/{id}"))
+        .body(Body::empty())
+        .unwrap();
+    send(router.clone(), remove).await;
+    let purge = |if_match: &str| {
+        Request::delete(format!("/19/purge/{id}"))
+            .header(header::IF_MATCH, if_match)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let (status, _) = send(router.clone(), purge(&current)).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let (status, _) = send(router, purge(&etag)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn if_match_can_be_required() {
+    let state = AppState {
+        require_if_match: true,
+        ..AppState::new(Arc::new(MemoryQuoteStore::default()))
+    };
+    let router = build_router(state);
+    let id = uuid::Uuid::new_v4();
+    for request in [
+        Request::delete(format!("/19/remove/{id}")),
+        Request::post(format!("/19/revert/{id}/1")),
+        Request::post(format!("/19/restore/{id}")),
+        Request::delete(format!("/19/purge/{id}")),
+    ] {
+        let (status, _) = send(router.clone(), request.body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::PRECONDITION_REQUIRED);
+    }
+}
+
+#[tokio::test]
+async fn admin_routes_require_credentials() {
+    let state = AppState {
+        admin: AdminAuth {
+            token: Some("hunter2".into()),
+            jwt_secret: Some("jwt-secret".into()),
+        },
+        ..AppState::new(Arc::new(MemoryQuoteStore::default()))
+    };
+    let router = build_router(state);
+    let reset = |credential: Option<&str>| {
+        let request = Request::post("/19/reset");
+        match credential {
+            Some(credential) => {
+                request.header(header::AUTHORIZATION, format!("Bearer {credential}"))
+            }
+            None => request,
+        }
+        .body(Body::empty())
+        .unwrap()
+    };
+    let jwt = |role: &str| {
+        jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({ "sub": "elf", "role": role, "exp": 4102444800u64 }),
+            &jsonwebtoken::EncodingKey::from_secret(b"jwt-secret"),
+        )
+        .unwrap()
+    };
+
+    let response = router.clone().oneshot(reset(None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+    let (status, _) = send(router.clone(), reset(Some("hunter3"))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(router.clone(), reset(Some(&jwt("viewer")))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(router.clone(), reset(Some("hunter2"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(router.clone(), reset(Some(&jwt("admin")))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let request = Request::post("/9/refill").body(Body::empty()).unwrap();
+    let (status, _) = send(router.clone(), request).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let request = Request::post("/12/reset").body(Body::empty()).unwrap();
+    let (status, _) = send(router.clone(), request).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let uri = format!("/12/games/{}/reset", new_game(&router, "").await);
+    let request = Request::post(&uri).body(Body::empty()).unwrap();
+    let (status, _) = send(router.clone(), request).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let request = Request::post(&uri)
+        .header(header::AUTHORIZATION, "Bearer hunter2")
+        .body(Body::empty())
+        .unwrap();
+    let (status, _) = send(router.clone(), request).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, body) = send(router, get("/19/audit")).await;
+    let log: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let actors: Vec<_> = log["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["actor"].as_str().unwrap())
+        .collect();
+    assert_eq!(actors, ["elf", "admin"]);
+}
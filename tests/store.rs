@@ -0,0 +1,744 @@
+//! Exercises every quote store backend through the `QuoteStore` trait.
+//!
+//! Every scenario runs once per backend. The Postgres runs are ignored unless
+//! asked for with `cargo test -- --include-ignored`, and then need
+//! `TEST_DATABASE_URL` to point at a database. It is reset on every run, so
+//! never point it at a database you care about.
+
+use std::sync::Arc;
+
+use chrono::{Duration, SubsecRound, Utc};
+use shuttlings_cch24::store::{
+    self, AuditQuery, AuditRecord, Quote, QuoteEventKind, QuoteStore, Rating, SearchQuery,
+    TagFilter, TagMatch, Vote, PURGE_ACTOR,
+};
+use uuid::Uuid;
+
+/// Generates one test per scenario and backend.
+macro_rules! store_tests {
+    ($($scenario:ident),* $(,)?) => {
+        mod memory {
+            $(
+                #[tokio::test]
+                async fn $scenario() {
+                    super::$scenario(super::store::connect("memory").await.unwrap()).await;
+                }
+            )*
+        }
+
+        mod sqlite {
+            $(
+                #[tokio::test]
+                async fn $scenario() {
+                    let quotes = super::store::connect("sqlite::memory:").await.unwrap();
+                    super::$scenario(quotes).await;
+                }
+            )*
+        }
+
+        mod postgres {
+            $(
+                #[tokio::test]
+                #[ignore = "needs TEST_DATABASE_URL"]
+                async fn $scenario() {
+                    // The scenarios share one database, so they take turns.
+                    let _turn = super::POSTGRES.lock().await;
+                    super::$scenario(super::postgres().await).await;
+                }
+            )*
+        }
+    };
+}
+
+store_tests!(
+    versions,
+    soft_delete,
+    purge_deleted_before,
+    list,
+    search,
+    import,
+    authors,
+    tags,
+    votes,
+    events,
+    audit,
+);
+
+static POSTGRES: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+async fn postgres() -> Arc<dyn QuoteStore> {
+    let url = std::env::var("TEST_DATABASE_URL")
+        .expect("TEST_DATABASE_URL must point at a Postgres database for the ignored tests");
+    store::connect(&url).await.unwrap()
+}
+
+async fn versions(quotes: Arc<dyn QuoteStore>) {
+    quotes.reset(None).await.unwrap();
+    let id = Uuid::new_v4();
+
+    let created = quotes.insert(id, "Santa", "Ho ho ho!", None).await.unwrap();
+    assert_eq!(created.id, id);
+    assert_eq!(created.version, 1);
+    assert_eq!(quotes.get(id).await.unwrap().unwrap().quote, "Ho ho ho!");
+
+    let updated = quotes
+        .update(id, "Santa", "I changed my mind...", Some(1), None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(updated.quote, "I changed my mind...");
+    assert_eq!(updated.version, 2);
+    // Stale writes are refused.
+    assert!(quotes
+        .update(id, "a", "b", Some(1), None)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(quotes.remove(id, Some(1), None).await.unwrap().is_none());
+
+    let history = quotes.history(id).await.unwrap().unwrap();
+    let versions: Vec<_> = history
+        .iter()
+        .map(|v| (v.version, v.quote.as_str()))
+        .collect();
+    assert_eq!(versions, [(1, "Ho ho ho!"), (2, "I changed my mind...")]);
+    assert_eq!(
+        quotes.version(id, 1).await.unwrap().unwrap().quote,
+        "Ho ho ho!"
+    );
+    assert!(quotes.version(id, 3).await.unwrap().is_none());
+
+    let reverted = quotes.revert(id, 1, None, None).await.unwrap().unwrap();
+    assert_eq!(reverted.quote, "Ho ho ho!");
+    assert_eq!(reverted.version, 3);
+    assert_eq!(quotes.history(id).await.unwrap().unwrap().len(), 3);
+    assert!(quotes.history(Uuid::new_v4()).await.unwrap().is_none());
+}
+
+async fn soft_delete(quotes: Arc<dyn QuoteStore>) {
+    quotes.reset(None).await.unwrap();
+    let id = Uuid::new_v4();
+    quotes.insert(id, "Santa", "Ho ho ho!", None).await.unwrap();
+
+    let removed = quotes.remove(id, None, None).await.unwrap().unwrap();
+    assert_eq!(removed.version, 1);
+    assert!(removed.deleted_at.is_some());
+    assert!(quotes.get(id).await.unwrap().is_none());
+    assert!(quotes
+        .update(id, "a", "b", None, None)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(quotes.remove(id, None, None).await.unwrap().is_none());
+    assert!(quotes.revert(id, 1, None, None).await.unwrap().is_none());
+    assert!(quotes.history(id).await.unwrap().is_none());
+    assert!(quotes.version(id, 1).await.unwrap().is_none());
+
+    assert_eq!(quotes.get_deleted(id).await.unwrap().unwrap().version, 1);
+    assert!(quotes.restore(id, Some(2), None).await.unwrap().is_none());
+    let restored = quotes.restore(id, Some(1), None).await.unwrap().unwrap();
+    assert!(restored.deleted_at.is_none());
+    assert!(quotes.get_deleted(id).await.unwrap().is_none());
+    assert!(quotes.revert(id, 1, Some(2), None).await.unwrap().is_none());
+    assert!(quotes.restore(id, None, None).await.unwrap().is_none());
+    assert!(quotes.purge(id, None, None).await.unwrap().is_none());
+    assert!(quotes.get(id).await.unwrap().is_some());
+
+    quotes.remove(id, None, None).await.unwrap().unwrap();
+    assert!(quotes.purge(id, Some(2), None).await.unwrap().is_none());
+    assert_eq!(
+        quotes.purge(id, Some(1), None).await.unwrap().unwrap().id,
+        id
+    );
+    assert!(quotes.restore(id, None, None).await.unwrap().is_none());
+    assert!(quotes.history(id).await.unwrap().is_none());
+}
+
+async fn purge_deleted_before(quotes: Arc<dyn QuoteStore>) {
+    quotes.reset(None).await.unwrap();
+    let kept = quotes
+        .insert(Uuid::new_v4(), "Grinch", "Bah", None)
+        .await
+        .unwrap();
+    let gone = quotes
+        .insert(Uuid::new_v4(), "Grinch", "Humbug", None)
+        .await
+        .unwrap();
+    quotes.remove(gone.id, None, None).await.unwrap().unwrap();
+
+    let cutoff = Utc::now() - Duration::days(1);
+    assert_eq!(quotes.purge_deleted_before(cutoff).await.unwrap(), 0);
+    let cutoff = Utc::now() + Duration::seconds(1);
+    assert_eq!(quotes.purge_deleted_before(cutoff).await.unwrap(), 1);
+    assert!(quotes.restore(gone.id, None, None).await.unwrap().is_none());
+    assert!(quotes.get(kept.id).await.unwrap().is_some());
+    // Events outlive quotes even with zero retention.
+    assert_eq!(quotes.purge_deleted_before(Utc::now()).await.unwrap(), 0);
+    assert_eq!(quotes.events_since(0).await.unwrap().len(), 3);
+}
+
+async fn list(quotes: Arc<dyn QuoteStore>) {
+    quotes.reset(None).await.unwrap();
+    for quote in ["one", "two", "three"] {
+        quotes
+            .insert(Uuid::new_v4(), "Grinch", quote, None)
+            .await
+            .unwrap();
+    }
+    let page = quotes.list(1, 5, &TagFilter::default()).await.unwrap();
+    let texts: Vec<_> = page.iter().map(|q| q.quote.as_str()).collect();
+    assert_eq!(texts, ["two", "three"]);
+    assert_eq!(quotes.count().await.unwrap(), 3);
+
+    quotes
+        .remove(page[0].id, None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    let texts: Vec<_> = quotes
+        .list(0, 5, &TagFilter::default())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|q| q.quote)
+        .collect();
+    assert_eq!(texts, ["one", "three"]);
+    assert_eq!(quotes.count().await.unwrap(), 2);
+
+    // Walking by cursor skips deleted quotes and isn't thrown off by them.
+    let first = quotes.list_after(None, 1).await.unwrap();
+    assert_eq!(first[0].quote, "one");
+    quotes
+        .remove(first[0].id, None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    let after = Some((first[0].created_at, first[0].id));
+    let rest: Vec<_> = quotes
+        .list_after(after, 5)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|q| q.quote)
+        .collect();
+    assert_eq!(rest, ["three"]);
+
+    quotes.reset(None).await.unwrap();
+    assert!(quotes
+        .list(0, 5, &TagFilter::default())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+async fn search(quotes: Arc<dyn QuoteStore>) {
+    quotes.reset(None).await.unwrap();
+    quotes
+        .insert(Uuid::new_v4(), "Santa", "Cookies and milk, please", None)
+        .await
+        .unwrap();
+    quotes
+        .insert(Uuid::new_v4(), "Rudolph", "Milk is for reindeer", None)
+        .await
+        .unwrap();
+    let search = |text: Option<&str>, author: Option<&str>| SearchQuery {
+        text: text.map(str::to_string),
+        author: author.map(str::to_string),
+        tags: TagFilter::default(),
+        offset: 0,
+        limit: 10,
+    };
+    assert_eq!(
+        quotes
+            .search(&search(Some("milk"), None))
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+    let hits = quotes
+        .search(&search(Some("milk cookies"), None))
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(
+        hits[0].snippet,
+        "<mark>Cookies</mark> and <mark>milk</mark>, please"
+    );
+    // Markup in quotes is escaped rather than passed through.
+    quotes
+        .insert(
+            Uuid::new_v4(),
+            "Elf",
+            "Milk <b>&</b> cookies aren't \"gifts\"",
+            None,
+        )
+        .await
+        .unwrap();
+    let hits = quotes.search(&search(Some("gifts"), None)).await.unwrap();
+    assert_eq!(
+        hits[0].snippet,
+        "Milk &lt;b&gt;&amp;&lt;&#x2F;b&gt; cookies aren&#x27;t &quot;<mark>gifts</mark>&quot;"
+    );
+    // Characters a backend might mark matches with don't unbalance the tags.
+    quotes
+        .insert(Uuid::new_v4(), "Elf", "Stray \u{E001}ribbons\u{E000}", None)
+        .await
+        .unwrap();
+    let hits = quotes.search(&search(Some("ribbons"), None)).await.unwrap();
+    assert_eq!(hits[0].snippet.matches("<mark>").count(), 1);
+    assert_eq!(hits[0].snippet.matches("</mark>").count(), 1);
+    let hits = quotes.search(&search(None, Some("rudolph"))).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].quote.author, "Rudolph");
+    assert!(quotes
+        .search(&search(Some("milk"), Some("Grinch")))
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+async fn import(quotes: Arc<dyn QuoteStore>) {
+    quotes.reset(None).await.unwrap();
+    let imported = Quote {
+        id: Uuid::new_v4(),
+        author: "Mrs. Claus".into(),
+        quote: "Who ate the cookies?".into(),
+        created_at: (Utc::now() - Duration::days(3)).trunc_subsecs(0),
+        version: 4,
+        deleted_at: None,
+    };
+    let fresh = Quote {
+        id: Uuid::new_v4(),
+        ..imported.clone()
+    };
+    assert!(quotes
+        .import(std::slice::from_ref(&imported), None)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(quotes.get(imported.id).await.unwrap().unwrap(), imported);
+    // A taken id fails the whole batch.
+    assert_eq!(
+        quotes
+            .import(&[fresh.clone(), imported.clone()], None)
+            .await
+            .unwrap(),
+        [imported.id]
+    );
+    assert!(quotes.get(fresh.id).await.unwrap().is_none());
+}
+
+async fn authors(quotes: Arc<dyn QuoteStore>) {
+    quotes.reset(None).await.unwrap();
+    for (author, quote) in [
+        ("Grinch", "Bah"),
+        ("Santa", "Cookies and milk, please"),
+        ("Rudolph", "Milk is for reindeer"),
+        ("Grinch", "Humbug"),
+    ] {
+        quotes
+            .insert(Uuid::new_v4(), author, quote, None)
+            .await
+            .unwrap();
+    }
+    // Differently written names end up with the same author.
+    let ho = quotes
+        .insert(Uuid::new_v4(), "  SANTA ", "Ho!", None)
+        .await
+        .unwrap();
+    // Refused updates file no author.
+    let stale = quotes.update(ho.id, "Krampus", "Boo", Some(2), None);
+    assert!(stale.await.unwrap().is_none());
+    let missing = quotes.update(Uuid::new_v4(), "Krampus", "Boo", None, None);
+    assert!(missing.await.unwrap().is_none());
+    let authors = quotes.authors().await.unwrap();
+    let counts: Vec<_> = authors
+        .iter()
+        .map(|a| (a.name.as_str(), a.quote_count))
+        .collect();
+    assert_eq!(counts, [("Grinch", 2), ("Rudolph", 1), ("Santa", 2)]);
+    let santa = &authors[2];
+    assert!(santa.first_quote_at < santa.last_quote_at);
+    let by_santa = quotes.quotes_by_author(santa.id).await.unwrap().unwrap();
+    let texts: Vec<_> = by_santa.iter().map(|q| q.quote.as_str()).collect();
+    assert_eq!(texts, ["Cookies and milk, please", "Ho!"]);
+    assert!(quotes
+        .quotes_by_author(Uuid::new_v4())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn tags(quotes: Arc<dyn QuoteStore>) {
+    quotes.reset(None).await.unwrap();
+    let wrap = quotes
+        .insert(Uuid::new_v4(), "Elf", "Wrap it up", None)
+        .await
+        .unwrap();
+    let bow = quotes
+        .insert(Uuid::new_v4(), "Elf", "Tie a bow", None)
+        .await
+        .unwrap();
+    quotes.tag(wrap.id, "work").await.unwrap().unwrap();
+    assert_eq!(
+        quotes.tag(wrap.id, "gifts").await.unwrap().unwrap(),
+        ["gifts", "work"]
+    );
+    quotes.tag(bow.id, "gifts").await.unwrap().unwrap();
+    let filter = |mode, tags: &[&str]| TagFilter {
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        mode,
+    };
+    let tagged = |filter: TagFilter| {
+        let quotes = quotes.clone();
+        async move {
+            let mut texts: Vec<_> = quotes
+                .list(0, 10, &filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|q| q.quote)
+                .collect();
+            texts.sort();
+            texts
+        }
+    };
+    assert_eq!(
+        tagged(filter(TagMatch::All, &["gifts", "work"])).await,
+        ["Wrap it up"]
+    );
+    assert_eq!(
+        tagged(filter(TagMatch::Any, &["work", "unknown"])).await,
+        ["Wrap it up"]
+    );
+    assert_eq!(
+        tagged(filter(TagMatch::Any, &["gifts"])).await,
+        ["Tie a bow", "Wrap it up"]
+    );
+    let hits = quotes
+        .search(&SearchQuery {
+            text: Some("wrap".into()),
+            tags: filter(TagMatch::All, &["gifts"]),
+            limit: 10,
+            ..SearchQuery::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+    let counts = |tags: Vec<store::TagCount>| {
+        tags.into_iter()
+            .map(|tag| (tag.name, tag.quote_count))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        counts(quotes.tags().await.unwrap()),
+        [("gifts".to_string(), 2), ("work".to_string(), 1)]
+    );
+    assert_eq!(
+        quotes.untag(wrap.id, "work").await.unwrap().unwrap(),
+        ["gifts"]
+    );
+    quotes.remove(bow.id, None, None).await.unwrap().unwrap();
+    assert_eq!(
+        counts(quotes.tags().await.unwrap()),
+        [("gifts".to_string(), 1)]
+    );
+    assert!(quotes.tag(bow.id, "gifts").await.unwrap().is_none());
+}
+
+async fn votes(quotes: Arc<dyn QuoteStore>) {
+    quotes.reset(None).await.unwrap();
+    let wrap = quotes
+        .insert(Uuid::new_v4(), "Elf", "Wrap it up", None)
+        .await
+        .unwrap();
+    let bow = quotes
+        .insert(Uuid::new_v4(), "Elf", "Tie a bow", None)
+        .await
+        .unwrap();
+    quotes.remove(bow.id, None, None).await.unwrap().unwrap();
+
+    let rating = |upvotes, downvotes| Rating { upvotes, downvotes };
+    for (voter, vote) in [("a", Vote::Up), ("b", Vote::Up), ("c", Vote::Down)] {
+        quotes.vote(wrap.id, voter, Some(vote)).await.unwrap();
+    }
+    assert_eq!(
+        quotes.vote(wrap.id, "c", Some(Vote::Up)).await.unwrap(),
+        Some(rating(3, 0))
+    );
+    assert_eq!(
+        quotes.vote(wrap.id, "b", None).await.unwrap(),
+        Some(rating(2, 0))
+    );
+    assert!(quotes
+        .vote(bow.id, "a", Some(Vote::Up))
+        .await
+        .unwrap()
+        .is_none());
+    let sleigh = quotes
+        .insert(Uuid::new_v4(), "Rudolph", "Sleigh all day", None)
+        .await
+        .unwrap();
+    for voter in ["a", "b", "c", "d", "e"] {
+        quotes.vote(sleigh.id, voter, Some(Vote::Up)).await.unwrap();
+    }
+    quotes.vote(sleigh.id, "f", Some(Vote::Down)).await.unwrap();
+    let ratings = quotes
+        .ratings(&[wrap.id, sleigh.id, Uuid::new_v4()])
+        .await
+        .unwrap();
+    assert_eq!(ratings.len(), 2);
+    assert_eq!(ratings[&sleigh.id], rating(5, 1));
+    // More votes outweigh a slightly lower upvote share.
+    let top: Vec<_> = quotes
+        .top(0, 10)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|rated| (rated.quote.id, rated.rating))
+        .collect();
+    assert_eq!(top, [(sleigh.id, rating(5, 1)), (wrap.id, rating(2, 0))]);
+    assert_eq!(quotes.top(1, 10).await.unwrap()[0].quote.id, wrap.id);
+}
+
+async fn events(quotes: Arc<dyn QuoteStore>) {
+    quotes.reset(None).await.unwrap();
+    quotes
+        .insert(Uuid::new_v4(), "Elf", "Earlier", None)
+        .await
+        .unwrap();
+    // Postgres listens from the moment the store is built, so no wait is
+    // needed before changes reach the feed.
+    let mut feed = quotes.subscribe();
+    let seen = quotes.events_since(0).await.unwrap().last().unwrap().id;
+    let noted = quotes
+        .insert(Uuid::new_v4(), "Elf", "Noted", None)
+        .await
+        .unwrap();
+    quotes
+        .update(noted.id, "Elf", "Noted!", None, None)
+        .await
+        .unwrap();
+    quotes.remove(noted.id, None, None).await.unwrap();
+    let events: Vec<_> = quotes
+        .events_since(seen)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|event| (event.kind, event.quote.version))
+        .collect();
+    assert_eq!(
+        events,
+        [
+            (QuoteEventKind::Created, 1),
+            (QuoteEventKind::Updated, 2),
+            (QuoteEventKind::Deleted, 2)
+        ]
+    );
+    // The feed may still be catching up on the earlier quote.
+    let next = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            let event = feed.recv().await.unwrap();
+            if event.quote.id == noted.id {
+                return event;
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        (next.kind, next.quote.id),
+        (QuoteEventKind::Created, noted.id)
+    );
+}
+
+async fn audit(quotes: Arc<dyn QuoteStore>) {
+    quotes.reset(None).await.unwrap();
+    let audited = Uuid::new_v4();
+    let record = |action: &str| AuditRecord {
+        action: action.to_string(),
+        quote_id: Some(audited),
+        before: None,
+        after: Some(serde_json::json!({ "quote": action })),
+        actor: Some("elf".into()),
+    };
+    let first = quotes.append_audit(&record("create")).await.unwrap();
+    let second = quotes.append_audit(&record("update")).await.unwrap();
+    assert!(second.id > first.id);
+
+    quotes.reset(None).await.unwrap();
+    let log = |query: AuditQuery| {
+        let quotes = quotes.clone();
+        async move {
+            let entries = quotes
+                .audit(&AuditQuery {
+                    quote_id: Some(audited),
+                    limit: 10,
+                    ..query
+                })
+                .await
+                .unwrap();
+            entries
+                .into_iter()
+                .map(|entry| entry.record)
+                .collect::<Vec<_>>()
+        }
+    };
+    // Newest first, and kept across resets.
+    assert_eq!(
+        log(AuditQuery::default()).await,
+        [record("update"), record("create")]
+    );
+    let since = Some(second.created_at);
+    assert_eq!(
+        log(AuditQuery {
+            since,
+            ..AuditQuery::default()
+        })
+        .await,
+        [record("update")]
+    );
+    assert!(log(AuditQuery {
+        until: Some(first.created_at),
+        ..AuditQuery::default()
+    })
+    .await
+    .is_empty());
+
+    // Mutations write their own record, with the acting client.
+    let id = Uuid::new_v4();
+    quotes
+        .insert(id, "Santa", "Ho!", Some("elf"))
+        .await
+        .unwrap();
+    quotes.remove(id, None, Some("grinch")).await.unwrap();
+    // So does the purge job, as the system.
+    let cutoff = Utc::now() + Duration::seconds(1);
+    assert_eq!(quotes.purge_deleted_before(cutoff).await.unwrap(), 1);
+    let entries = quotes
+        .audit(&AuditQuery {
+            quote_id: Some(id),
+            limit: 10,
+            ..AuditQuery::default()
+        })
+        .await
+        .unwrap();
+    let actions = entries
+        .iter()
+        .map(|entry| (entry.record.action.as_str(), entry.record.actor.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        actions,
+        [
+            ("purge", Some(PURGE_ACTOR)),
+            ("delete", Some("grinch")),
+            ("create", Some("elf"))
+        ]
+    );
+}
+
+/// Statements leaving quotes the way the authors migration found them.
+const UNFILE_AUTHORS: [&str; 2] = ["UPDATE quotes SET author_id = NULL", "DELETE FROM authors"];
+
+/// Checks that reconnecting files quotes `unfile` left without an author.
+async fn files_existing_quotes_under_authors(url: &str, unfile: impl AsyncFnOnce()) {
+    let quotes = store::connect(url).await.unwrap();
+    quotes.reset(None).await.unwrap();
+    for author in ["Santa", "  SANTA\tCLAUS ", "santa \n claus", "Grinch"] {
+        quotes
+            .insert(Uuid::new_v4(), author, "Ho!", None)
+            .await
+            .unwrap();
+    }
+    unfile().await;
+
+    let quotes = store::connect(url).await.unwrap();
+    let authors = quotes.authors().await.unwrap();
+    let counts: Vec<_> = authors
+        .iter()
+        .map(|a| (a.name.as_str(), a.quote_count))
+        .collect();
+    assert_eq!(counts, [("Grinch", 1), ("Santa", 1), ("SANTA\tCLAUS", 2)]);
+}
+
+#[tokio::test]
+async fn sqlite_files_existing_quotes_under_authors() {
+    let path = std::env::temp_dir().join(format!("quotes-{}.db", Uuid::new_v4()));
+    let url = format!("sqlite:{}", path.display());
+    files_existing_quotes_under_authors(&url, async || {
+        let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+        for statement in UNFILE_AUTHORS {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+    })
+    .await;
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn postgres_files_existing_quotes_under_authors() {
+    let _turn = POSTGRES.lock().await;
+    let url = std::env::var("TEST_DATABASE_URL")
+        .expect("TEST_DATABASE_URL must point at a Postgres database for the ignored tests");
+    files_existing_quotes_under_authors(&url, async || {
+        let pool = sqlx::PgPool::connect(&url).await.unwrap();
+        for statement in UNFILE_AUTHORS {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn purge_job_accepts_zero_retention() {
+    let quotes = store::connect("memory").await.unwrap();
+    let id = Uuid::new_v4();
+    quotes.insert(id, "Grinch", "Bah", None).await.unwrap();
+    quotes.remove(id, None, None).await.unwrap().unwrap();
+
+    let job = store::spawn_purge_job(quotes.clone(), std::time::Duration::ZERO);
+    // The first purge runs right away.
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    assert!(!job.is_finished());
+    assert!(quotes.restore(id, None, None).await.unwrap().is_none());
+    job.abort();
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn postgres_forwards_events_committed_out_of_order() {
+    let _turn = POSTGRES.lock().await;
+    let quotes = postgres().await;
+    quotes.reset(None).await.unwrap();
+    let mut feed = quotes.subscribe();
+    let url = std::env::var("TEST_DATABASE_URL").unwrap();
+    let pool = sqlx::PgPool::connect(&url).await.unwrap();
+    let insert = "INSERT INTO quotes (id, author, quote) VALUES ($1, 'Elf', 'Ho!')";
+
+    // The first write takes the lower event id but commits last.
+    let (early, late) = (Uuid::new_v4(), Uuid::new_v4());
+    let mut slow = pool.begin().await.unwrap();
+    sqlx::query(insert)
+        .bind(late)
+        .execute(&mut *slow)
+        .await
+        .unwrap();
+    sqlx::query(insert)
+        .bind(early)
+        .execute(&pool)
+        .await
+        .unwrap();
+    slow.commit().await.unwrap();
+
+    let mut seen = Vec::new();
+    while seen.len() < 2 {
+        let event = tokio::time::timeout(std::time::Duration::from_secs(5), feed.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        seen.push(event.quote.id);
+    }
+    assert_eq!(seen, [early, late]);
+}